@@ -0,0 +1,457 @@
+//! 快捷键字符串
+//!
+//! 支持两种写法：
+//! - `"Ctrl+Shift+F9"`、`"Win+Alt+Space"`（不区分大小写，`+` 两侧可以有空格）
+//! - AutoHotkey 风格 `"^!t"`：`^` Ctrl、`!` Alt、`+` Shift、`#` Win
+//!
+//! Ctrl、Alt、Shift 可以限定左右：`"LCtrl+T"`、`"RAlt+F9"`，AutoHotkey 风格为 `"<^t"`、`">!F9"`。
+
+use crate::error::HotkeyError;
+use crate::keys::{Key, MOD_ALT, MOD_CONTROL, MOD_SHIFT, MOD_WIN};
+use std::fmt;
+use std::str::FromStr;
+
+/// 修饰键的规范名称
+pub const MODIFIER_NAMES: [&str; 4] = ["Ctrl", "Alt", "Shift", "Win"];
+
+/// 修饰键
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Modifier {
+    Ctrl,
+    Alt,
+    Shift,
+    Win,
+}
+
+/// 修饰键限定的一侧
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Side {
+    /// 左右都可以
+    #[default]
+    Either,
+    Left,
+    Right,
+}
+
+impl Side {
+    /// 配置文件表格写法中的值：`"left"`、`"right"`
+    pub fn from_name(name: &str) -> Option<Side> {
+        match name.to_lowercase().as_str() {
+            "left" => Some(Side::Left),
+            "right" => Some(Side::Right),
+            _ => None,
+        }
+    }
+
+    /// `"left"`、`"right"`，不限定时为 None
+    pub fn name(&self) -> Option<&'static str> {
+        match self {
+            Side::Either => None,
+            Side::Left => Some("left"),
+            Side::Right => Some("right"),
+        }
+    }
+
+    /// 快捷键字符串中的前缀：`L`、`R`
+    pub(crate) fn prefix(&self) -> &'static str {
+        match self {
+            Side::Either => "",
+            Side::Left => "L",
+            Side::Right => "R",
+        }
+    }
+}
+
+/// Ctrl、Alt、Shift 各自限定的一侧（Win 键不区分左右）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Sides {
+    pub ctrl: Side,
+    pub alt: Side,
+    pub shift: Side,
+}
+
+impl Sides {
+    /// 是否有限定左右的修饰键
+    pub fn any(&self) -> bool {
+        *self != Sides::default()
+    }
+}
+
+impl Modifier {
+    /// 修饰键名称，可以带 `L`/`Left`、`R`/`Right` 前缀
+    fn from_name(name: &str) -> Option<(Modifier, Side)> {
+        let upper = name.to_uppercase();
+        let sided = [
+            ("LEFT", Side::Left),
+            ("RIGHT", Side::Right),
+            ("L", Side::Left),
+            ("R", Side::Right),
+        ]
+        .into_iter()
+        .find_map(|(prefix, side)| Some((upper.strip_prefix(prefix)?, side)));
+        let plain = |name: &str| {
+            Some(match name {
+                "CTRL" | "CONTROL" => Modifier::Ctrl,
+                "ALT" => Modifier::Alt,
+                "SHIFT" => Modifier::Shift,
+                "WIN" | "WINDOWS" | "SUPER" => Modifier::Win,
+                _ => return None,
+            })
+        };
+        match plain(&upper) {
+            Some(modifier) => Some((modifier, Side::Either)),
+            None => {
+                let (rest, side) = sided?;
+                plain(rest)
+                    .filter(|m| *m != Modifier::Win)
+                    .map(|m| (m, side))
+            }
+        }
+    }
+
+    fn from_ahk(c: char) -> Option<Modifier> {
+        Some(match c {
+            '^' => Modifier::Ctrl,
+            '!' => Modifier::Alt,
+            '+' => Modifier::Shift,
+            '#' => Modifier::Win,
+            _ => return None,
+        })
+    }
+}
+
+/// 快捷键组合：修饰键 + 主键
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Accelerator {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub win: bool,
+    /// 限定左右的修饰键，只对打开的修饰键有意义
+    pub sides: Sides,
+    pub key: Key,
+}
+
+impl Accelerator {
+    /// 只有主键、没有修饰键
+    pub fn new(key: Key) -> Self {
+        Self {
+            ctrl: false,
+            alt: false,
+            shift: false,
+            win: false,
+            sides: Sides::default(),
+            key,
+        }
+    }
+
+    /// 修饰键标志（`MOD_*`，不含 `MOD_NOREPEAT`）
+    pub fn modifiers(&self) -> u32 {
+        let mut mods = 0;
+        if self.ctrl {
+            mods |= MOD_CONTROL;
+        }
+        if self.alt {
+            mods |= MOD_ALT;
+        }
+        if self.shift {
+            mods |= MOD_SHIFT;
+        }
+        if self.win {
+            mods |= MOD_WIN;
+        }
+        mods
+    }
+
+    /// 打开修饰键，已经打开时返回 false
+    fn set(&mut self, modifier: Modifier, side: Side) -> bool {
+        let (flag, sided) = match modifier {
+            Modifier::Ctrl => (&mut self.ctrl, Some(&mut self.sides.ctrl)),
+            Modifier::Alt => (&mut self.alt, Some(&mut self.sides.alt)),
+            Modifier::Shift => (&mut self.shift, Some(&mut self.sides.shift)),
+            Modifier::Win => (&mut self.win, None),
+        };
+        if let Some(sided) = sided {
+            *sided = side;
+        }
+        !std::mem::replace(flag, true)
+    }
+
+    /// 两个快捷键是否会被同一次按键触发；分别限定左右两侧的不算
+    pub fn overlaps(&self, other: &Accelerator) -> bool {
+        let sides = |a: Side, b: Side| a == Side::Either || b == Side::Either || a == b;
+        self.modifiers() == other.modifiers()
+            && self.key == other.key
+            && sides(self.sides.ctrl, other.sides.ctrl)
+            && sides(self.sides.alt, other.sides.alt)
+            && sides(self.sides.shift, other.sides.shift)
+    }
+
+    /// 显示给用户的写法：扫描码按键显示为当前键盘布局上的名称
+    pub fn label(&self) -> String {
+        let text = self.to_string();
+        match self.key {
+            Key::Scan(_) => {
+                let prefix = &text[..text.len() - self.key.name().len()];
+                format!("{}{}", prefix, self.key.label())
+            }
+            _ => text,
+        }
+    }
+
+    /// 解析 AutoHotkey 风格的写法
+    fn parse_ahk(input: &str) -> Result<Self, HotkeyError> {
+        let mut accel = Accelerator::new(Key::Space);
+        let mut rest = input;
+        // 至少留一个字符作为主键，因此 "^+" 是 Ctrl + "+"
+        while let Some(c) = rest.chars().next().filter(|_| rest.chars().count() > 1) {
+            let column = input.len() - rest.len();
+            // `<`、`>` 限定左右，后面还要有修饰键和主键
+            let side = match c {
+                '<' => Side::Left,
+                '>' => Side::Right,
+                _ => Side::Either,
+            };
+            let (width, modifier) = if side == Side::Either {
+                (1, Modifier::from_ahk(c))
+            } else {
+                let next = rest[1..]
+                    .chars()
+                    .next()
+                    .filter(|_| rest.chars().count() > 2);
+                (2, next.and_then(Modifier::from_ahk))
+            };
+            let Some(modifier) = modifier else {
+                break;
+            };
+            let token = &rest[..width];
+            if modifier == Modifier::Win && side != Side::Either {
+                return Err(syntax_error(input, column, token, "Win 键不区分左右"));
+            }
+            if !accel.set(modifier, side) {
+                return Err(syntax_error(input, column, token, "重复的修饰键"));
+            }
+            rest = &rest[token.len()..];
+        }
+
+        let column = input.len() - rest.len();
+        accel.key = Key::from_name(rest)
+            .ok_or_else(|| syntax_error(input, column, rest, "不是有效的按键"))?;
+        Ok(accel)
+    }
+
+    /// 解析 `Ctrl+Alt+T` 写法
+    fn parse_plus(input: &str) -> Result<Self, HotkeyError> {
+        let tokens = split_plus(input);
+        let (&(key_offset, key_token), modifiers) = tokens.split_last().expect("至少有一段");
+
+        let mut accel = Accelerator::new(Key::Space);
+        for &(offset, token) in modifiers {
+            match Modifier::from_name(token) {
+                Some((modifier, side)) => {
+                    if !accel.set(modifier, side) {
+                        return Err(syntax_error(input, offset, token, "重复的修饰键"));
+                    }
+                }
+                None if Key::from_name(token).is_some() => {
+                    return Err(syntax_error(input, offset, token, "只能有一个主键"));
+                }
+                None => return Err(syntax_error(input, offset, token, "不是修饰键")),
+            }
+        }
+
+        if key_token.is_empty() || Modifier::from_name(key_token).is_some() {
+            return Err(syntax_error(input, key_offset, key_token, "缺少主键"));
+        }
+        accel.key = Key::from_name(key_token)
+            .ok_or_else(|| syntax_error(input, key_offset, key_token, "不是有效的按键"))?;
+        Ok(accel)
+    }
+}
+
+/// 按 `+` 分段，返回（字节偏移，去掉空格的内容）
+///
+/// 结尾单独的 `+` 当作按键，如 `+`、`Ctrl++`、`Ctrl + +`。
+fn split_plus(input: &str) -> Vec<(usize, &str)> {
+    let body = input.trim_end();
+    let head = body
+        .strip_suffix('+')
+        .map(str::trim_end)
+        .filter(|rest| rest.is_empty() || rest.ends_with('+'));
+    let Some(head) = head else {
+        return split_segments(input, input.len());
+    };
+
+    let mut tokens = match head.strip_suffix('+') {
+        Some(modifiers) => split_segments(input, modifiers.len()),
+        None => Vec::new(),
+    };
+    tokens.push((body.len() - 1, "+"));
+    tokens
+}
+
+/// 把 `input[..end]` 按 `+` 分段
+fn split_segments(input: &str, end: usize) -> Vec<(usize, &str)> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    for (i, c) in input[..end].char_indices() {
+        if c == '+' {
+            tokens.push(trim_token(input, start, i));
+            start = i + 1;
+        }
+    }
+    tokens.push(trim_token(input, start, end));
+    tokens
+}
+
+fn trim_token(input: &str, start: usize, end: usize) -> (usize, &str) {
+    let raw = &input[start..end];
+    let trimmed = raw.trim_start();
+    (start + raw.len() - trimmed.len(), trimmed.trim_end())
+}
+
+fn syntax_error(input: &str, offset: usize, token: &str, message: &str) -> HotkeyError {
+    HotkeyError::Syntax {
+        input: input.to_string(),
+        column: input[..offset].chars().count() + 1,
+        token: token.to_string(),
+        message: message.to_string(),
+    }
+}
+
+impl FromStr for Accelerator {
+    type Err = HotkeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let input = s.trim();
+        let ahk = input
+            .chars()
+            .next()
+            .is_some_and(|c| Modifier::from_ahk(c).is_some() || c == '<' || c == '>');
+        if ahk && input.chars().count() > 1 {
+            Self::parse_ahk(input)
+        } else {
+            Self::parse_plus(input)
+        }
+    }
+}
+
+/// 规范写法：`Ctrl+Alt+Shift+Win+键`，限定左右的写成 `LCtrl`、`RAlt`
+impl fmt::Display for Accelerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let modifiers = [
+            (self.ctrl, self.sides.ctrl, "Ctrl"),
+            (self.alt, self.sides.alt, "Alt"),
+            (self.shift, self.sides.shift, "Shift"),
+            (self.win, Side::Either, "Win"),
+        ];
+        for (_, side, name) in modifiers.iter().filter(|(on, _, _)| *on) {
+            write!(f, "{}{}+", side.prefix(), name)?;
+        }
+        write!(f, "{}", self.key.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Accelerator {
+        s.parse().unwrap()
+    }
+
+    fn error(s: &str) -> (usize, String, String) {
+        match s.parse::<Accelerator>() {
+            Err(HotkeyError::Syntax {
+                column,
+                token,
+                message,
+                ..
+            }) => (column, token, message),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_plus_form() {
+        let accel = parse("Ctrl+Shift+F9");
+        assert!(accel.ctrl && accel.shift && !accel.alt && !accel.win);
+        assert_eq!(accel.key, Key::F(9));
+
+        let accel = parse(" win + alt + space ");
+        assert!(accel.win && accel.alt);
+        assert_eq!(accel.key, Key::Space);
+
+        assert_eq!(parse("F9"), Accelerator::new(Key::F(9)));
+        assert_eq!(parse("Ctrl++").key, Key::Equal);
+        assert_eq!(parse("Ctrl + +"), parse("Ctrl++"));
+        assert_eq!(parse("+"), Accelerator::new(Key::Equal));
+        assert_eq!(parse("Shift+F13").key, Key::F(13));
+        assert_eq!(parse("Ctrl+vk:0xE8").to_string(), "Ctrl+vk:0xE8");
+    }
+
+    #[test]
+    fn parses_autohotkey_form() {
+        assert_eq!(parse("^!t"), parse("Ctrl+Alt+T"));
+        assert_eq!(parse("#Space"), parse("Win+Space"));
+        assert_eq!(parse("+F1"), parse("Shift+F1"));
+        assert_eq!(parse("^+"), parse("Ctrl+="));
+        assert_eq!(parse("<^>!t"), parse("LCtrl+RAlt+T"));
+        assert_eq!(parse("^<"), parse("Ctrl+,"));
+    }
+
+    #[test]
+    fn parses_left_and_right_modifiers() {
+        let accel = parse("LeftCtrl+rshift+T");
+        assert!(accel.ctrl && accel.shift);
+        assert_eq!(accel.sides.ctrl, Side::Left);
+        assert_eq!(accel.sides.shift, Side::Right);
+        assert_eq!(accel.sides.alt, Side::Either);
+        assert_eq!(accel.to_string(), "LCtrl+RShift+T");
+        assert_ne!(accel, parse("Ctrl+Shift+T"));
+        assert_eq!(accel.modifiers(), parse("Ctrl+Shift+T").modifiers());
+
+        assert_eq!(
+            error("LCtrl+RCtrl+T"),
+            (7, "RCtrl".into(), "重复的修饰键".into())
+        );
+        assert_eq!(error("LWin+T"), (1, "LWin".into(), "不是修饰键".into()));
+        assert_eq!(error("<#t"), (1, "<#".into(), "Win 键不区分左右".into()));
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for s in [
+            "Ctrl+Shift+F9",
+            "Alt+Win+Space",
+            "T",
+            "Ctrl+=",
+            "Shift+/",
+            "RAlt+sc:0x35",
+        ] {
+            assert_eq!(parse(s).to_string(), s);
+        }
+        assert_eq!(parse("^!t").to_string(), "Ctrl+Alt+T");
+        assert_eq!(parse("win+ctrl+a").to_string(), "Ctrl+Win+A");
+    }
+
+    #[test]
+    fn errors_point_at_bad_token() {
+        assert_eq!(
+            error("Ctrl+Spcae"),
+            (6, "Spcae".into(), "不是有效的按键".into())
+        );
+        assert_eq!(
+            error("Ctrl + Ctrl + T"),
+            (8, "Ctrl".into(), "重复的修饰键".into())
+        );
+        assert_eq!(error("Crtl+T"), (1, "Crtl".into(), "不是修饰键".into()));
+        assert_eq!(error("A+B"), (1, "A".into(), "只能有一个主键".into()));
+        assert_eq!(error("Ctrl+Alt"), (6, "Alt".into(), "缺少主键".into()));
+        assert_eq!(error("Ctrl+"), (6, "".into(), "缺少主键".into()));
+        assert_eq!(error("Ctlr + +"), (1, "Ctlr".into(), "不是修饰键".into()));
+        assert_eq!(error("Ctrl+A++"), (6, "A".into(), "只能有一个主键".into()));
+        assert_eq!(error("^^t"), (2, "^".into(), "重复的修饰键".into()));
+        assert_eq!(error("^!zz"), (3, "zz".into(), "不是有效的按键".into()));
+    }
+}
@@ -0,0 +1,430 @@
+//! 动作定义与分发
+//!
+//! 快捷键、托盘菜单以及外部触发都转换为 [`Action`]，统一交给 [`Dispatcher`] 执行。
+
+use crate::config::Settings;
+use crate::platform::{WindowBackend, WindowId};
+use crate::state::{AppState, PinnedWindow};
+use crate::window::{self, Feedback, ToggleOutcome};
+use crate::Pinner;
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+
+/// 动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// 切换前台窗口的置顶状态
+    Toggle,
+    /// 置顶前台窗口
+    Pin,
+    /// 取消前台窗口置顶
+    Unpin,
+    /// 取消所有由本工具置顶的窗口
+    UnpinAll,
+    /// 打开配置文件
+    OpenConfig,
+    /// 重新加载配置
+    Reload,
+    /// 退出程序
+    Exit,
+}
+
+impl Action {
+    /// 所有动作
+    pub const ALL: [Action; 7] = [
+        Action::Toggle,
+        Action::Pin,
+        Action::Unpin,
+        Action::UnpinAll,
+        Action::OpenConfig,
+        Action::Reload,
+        Action::Exit,
+    ];
+
+    /// 配置文件中使用的名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Toggle => "toggle",
+            Action::Pin => "pin",
+            Action::Unpin => "unpin",
+            Action::UnpinAll => "unpin_all",
+            Action::OpenConfig => "open_config",
+            Action::Reload => "reload",
+            Action::Exit => "exit",
+        }
+    }
+
+    /// 提示中显示的说明
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::Toggle => "切换置顶",
+            Action::Pin => "置顶",
+            Action::Unpin => "取消置顶",
+            Action::UnpinAll => "取消全部置顶",
+            Action::OpenConfig => "打开配置文件",
+            Action::Reload => "重新加载配置",
+            Action::Exit => "退出",
+        }
+    }
+
+    /// 按名称查找动作
+    pub fn from_name(name: &str) -> Option<Action> {
+        Self::ALL.into_iter().find(|a| a.name() == name)
+    }
+}
+
+/// 动作来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    /// 全局快捷键
+    Hotkey,
+    /// 托盘菜单或托盘图标
+    Menu,
+    /// 外部触发（命令行等）
+    External,
+}
+
+/// 动作执行环境：由使用者提供提示和程序级操作
+pub trait ActionHost {
+    /// 当前设置
+    fn settings(&self) -> &Settings;
+
+    /// 给出提示音和通知
+    fn feedback(&mut self, feedback: Feedback);
+
+    /// 打开配置文件
+    fn open_config(&mut self);
+
+    /// 重新加载配置
+    fn reload(&mut self);
+
+    /// 退出程序
+    fn exit(&mut self);
+}
+
+/// 动作分发器
+pub struct Dispatcher<B: WindowBackend> {
+    pinner: Pinner<B>,
+    state: AppState,
+}
+
+impl<B: WindowBackend> Dispatcher<B> {
+    /// 使用指定的窗口后端
+    pub fn new(backend: B) -> Self {
+        Self {
+            pinner: Pinner::new(backend),
+            state: AppState::new(),
+        }
+    }
+
+    /// 置顶器
+    pub fn pinner(&self) -> &Pinner<B> {
+        &self.pinner
+    }
+
+    /// 置顶器（可变）
+    pub fn pinner_mut(&mut self) -> &mut Pinner<B> {
+        &mut self.pinner
+    }
+
+    /// 置顶窗口登记表
+    pub fn state(&self) -> &AppState {
+        &self.state
+    }
+
+    /// 由本工具置顶且仍然存在的窗口
+    pub fn pinned(&mut self) -> &[PinnedWindow] {
+        self.state.prune(self.pinner.backend());
+        self.state.pinned()
+    }
+
+    /// 执行动作
+    pub fn dispatch(&mut self, action: Action, source: Source, host: &mut dyn ActionHost) {
+        self.state.prune(self.pinner.backend());
+        match action {
+            Action::Toggle => self.set_foreground(None, source, host),
+            Action::Pin => self.set_foreground(Some(true), source, host),
+            Action::Unpin => self.set_foreground(Some(false), source, host),
+            Action::UnpinAll => self.unpin_all(host),
+            Action::OpenConfig => host.open_config(),
+            Action::Reload => host.reload(),
+            Action::Exit => host.exit(),
+        }
+    }
+
+    /// 修改前台窗口的置顶状态，`topmost` 为 None 时切换
+    fn set_foreground(&mut self, topmost: Option<bool>, source: Source, host: &mut dyn ActionHost) {
+        let outcome = match self.pinner.backend().foreground() {
+            Some(window) => self.set_window(window, topmost, source, host.settings().max_pinned),
+            None => ToggleOutcome::NoWindow,
+        };
+        let feedback = outcome.feedback(host.settings());
+        host.feedback(feedback);
+    }
+
+    /// 修改窗口置顶状态并更新登记表，`max_pinned` 为置顶窗口数上限
+    fn set_window(
+        &mut self,
+        window: WindowId,
+        topmost: Option<bool>,
+        source: Source,
+        max_pinned: Option<usize>,
+    ) -> ToggleOutcome {
+        let backend = self.pinner.backend_mut();
+        let was_topmost = backend.is_topmost(window);
+        let target = topmost.unwrap_or(!was_topmost);
+
+        // 不取消别人设置的置顶
+        if was_topmost && !target && !self.state.is_ours(window) {
+            let title = backend.title(window);
+            return ToggleOutcome::NotOurs {
+                title: title.unwrap_or_else(|| window::UNTITLED.into()),
+            };
+        }
+        if let Some(max) = max_pinned {
+            if target && !was_topmost && self.state.pinned().len() >= max {
+                return ToggleOutcome::LimitReached { max };
+            }
+        }
+
+        let outcome = window::set_topmost(backend, window, Some(target));
+        match &outcome {
+            // 原本就置顶的窗口不登记
+            ToggleOutcome::Pinned { title } if !was_topmost => {
+                self.state.record_pin(PinnedWindow {
+                    window,
+                    process: backend.process(window),
+                    title: title.clone(),
+                    pinned_at: SystemTime::now(),
+                    source,
+                });
+            }
+            ToggleOutcome::Unpinned { .. } => {
+                self.state.remove(window);
+            }
+            _ => {}
+        }
+        outcome
+    }
+
+    /// 取消所有由本工具置顶的窗口
+    fn unpin_all(&mut self, host: &mut dyn ActionHost) {
+        let mut count = 0;
+        let windows: Vec<_> = self.state.pinned().iter().map(|p| p.window).collect();
+        for window in windows {
+            if self.pinner.unpin(window).is_ok() {
+                self.state.remove(window);
+                count += 1;
+            }
+        }
+
+        let settings = host.settings();
+        let feedback = if count == 0 {
+            Feedback {
+                beep: None,
+                notification: Some(("窗口置顶工具".into(), "没有置顶的窗口".into())),
+            }
+        } else {
+            Feedback {
+                beep: settings.play_sound.then_some((600, 100)),
+                notification: settings.show_notification.then(|| {
+                    (
+                        "取消全部置顶".into(),
+                        format!("已取消 {} 个窗口的置顶", count),
+                    )
+                }),
+            }
+        };
+        host.feedback(feedback);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::sim::SimDesktop;
+
+    /// 记录所有调用的执行环境
+    #[derive(Default)]
+    struct RecordingHost {
+        settings: Settings,
+        feedback: Vec<Feedback>,
+        calls: Vec<&'static str>,
+    }
+
+    impl ActionHost for RecordingHost {
+        fn settings(&self) -> &Settings {
+            &self.settings
+        }
+
+        fn feedback(&mut self, feedback: Feedback) {
+            self.feedback.push(feedback);
+        }
+
+        fn open_config(&mut self) {
+            self.calls.push("open_config");
+        }
+
+        fn reload(&mut self) {
+            self.calls.push("reload");
+        }
+
+        fn exit(&mut self) {
+            self.calls.push("exit");
+        }
+    }
+
+    fn notification(feedback: &Feedback) -> (&str, &str) {
+        let (title, body) = feedback.notification.as_ref().unwrap();
+        (title, body)
+    }
+
+    #[test]
+    fn action_names_match_serde() {
+        for action in Action::ALL {
+            let quoted = serde_json::to_string(&action).unwrap();
+            assert_eq!(quoted, format!("\"{}\"", action.name()));
+            assert_eq!(Action::from_name(action.name()), Some(action));
+        }
+        assert_eq!(Action::from_name("bogus"), None);
+    }
+
+    #[test]
+    fn pin_and_unpin_are_idempotent() {
+        let mut desktop = SimDesktop::new();
+        let window = desktop.open("编辑器");
+        let mut dispatcher = Dispatcher::new(desktop);
+        let mut host = RecordingHost::default();
+
+        dispatcher.dispatch(Action::Pin, Source::Hotkey, &mut host);
+        dispatcher.dispatch(Action::Pin, Source::Hotkey, &mut host);
+        assert!(dispatcher.pinner().is_pinned(window));
+        assert_eq!(dispatcher.pinned().len(), 1);
+        assert_eq!(notification(&host.feedback[1]), ("已置顶", "编辑器"));
+
+        dispatcher.dispatch(Action::Unpin, Source::Menu, &mut host);
+        dispatcher.dispatch(Action::Unpin, Source::Menu, &mut host);
+        assert!(!dispatcher.pinner().is_pinned(window));
+        assert!(dispatcher.pinned().is_empty());
+    }
+
+    #[test]
+    fn unpin_all_leaves_foreign_topmost_windows_alone() {
+        let mut desktop = SimDesktop::new();
+        let taskbar = desktop.open("任务栏");
+        desktop.set_topmost(taskbar, true).unwrap();
+        let a = desktop.open("a");
+        let b = desktop.open("b");
+
+        let mut dispatcher = Dispatcher::new(desktop);
+        let mut host = RecordingHost::default();
+        dispatcher.dispatch(Action::Toggle, Source::Hotkey, &mut host);
+        assert!(dispatcher.pinner().is_pinned(b));
+
+        dispatcher.dispatch(Action::UnpinAll, Source::Hotkey, &mut host);
+        assert!(!dispatcher.pinner().is_pinned(b));
+        assert!(!dispatcher.pinner().is_pinned(a));
+        assert!(dispatcher.pinner().is_pinned(taskbar));
+        assert_eq!(
+            notification(host.feedback.last().unwrap()),
+            ("取消全部置顶", "已取消 1 个窗口的置顶")
+        );
+
+        dispatcher.dispatch(Action::UnpinAll, Source::Hotkey, &mut host);
+        assert_eq!(
+            notification(host.feedback.last().unwrap()),
+            ("窗口置顶工具", "没有置顶的窗口")
+        );
+    }
+
+    #[test]
+    fn program_actions_go_to_host() {
+        let mut dispatcher = Dispatcher::new(SimDesktop::new());
+        let mut host = RecordingHost::default();
+        for action in [Action::OpenConfig, Action::Reload, Action::Exit] {
+            dispatcher.dispatch(action, Source::External, &mut host);
+        }
+        assert_eq!(host.calls, vec!["open_config", "reload", "exit"]);
+        assert!(host.feedback.is_empty());
+    }
+
+    #[test]
+    fn registry_records_pin_metadata() {
+        let mut desktop = SimDesktop::new();
+        let window = desktop.open("播放器");
+        desktop.set_process(window, 42, "player.exe");
+        let mut dispatcher = Dispatcher::new(desktop);
+        let mut host = RecordingHost::default();
+
+        dispatcher.dispatch(Action::Toggle, Source::Menu, &mut host);
+        let entry = &dispatcher.pinned()[0];
+        assert_eq!(entry.window, window);
+        assert_eq!(entry.title, "播放器");
+        assert_eq!(entry.source, Source::Menu);
+        assert_eq!(entry.process.as_ref().unwrap().name, "player.exe");
+    }
+
+    #[test]
+    fn never_demotes_windows_that_were_already_topmost() {
+        let mut desktop = SimDesktop::new();
+        let clock = desktop.open("时钟");
+        desktop.set_topmost(clock, true).unwrap();
+        let mut dispatcher = Dispatcher::new(desktop);
+        let mut host = RecordingHost::default();
+
+        // 置顶一个本来就置顶的窗口不会登记
+        dispatcher.dispatch(Action::Pin, Source::Hotkey, &mut host);
+        assert!(dispatcher.pinned().is_empty());
+
+        for action in [Action::Toggle, Action::Unpin, Action::UnpinAll] {
+            dispatcher.dispatch(action, Source::Hotkey, &mut host);
+            assert!(dispatcher.pinner().is_pinned(clock));
+        }
+        assert_eq!(
+            notification(&host.feedback[1]),
+            ("未取消置顶", "时钟 不是由本工具置顶的")
+        );
+    }
+
+    #[test]
+    fn pinning_stops_at_the_configured_limit() {
+        let mut desktop = SimDesktop::new();
+        let a = desktop.open("a");
+        let b = desktop.open("b");
+        let mut dispatcher = Dispatcher::new(desktop);
+        let mut host = RecordingHost::default();
+        host.settings.max_pinned = Some(1);
+
+        dispatcher.dispatch(Action::Pin, Source::Hotkey, &mut host);
+        dispatcher.pinner_mut().backend_mut().focus(a);
+        dispatcher.dispatch(Action::Pin, Source::Hotkey, &mut host);
+        assert!(dispatcher.pinner().is_pinned(b));
+        assert!(!dispatcher.pinner().is_pinned(a));
+        assert_eq!(
+            notification(&host.feedback[1]),
+            ("未置顶", "最多只能同时置顶 1 个窗口")
+        );
+
+        // 取消后可以置顶其他窗口
+        dispatcher.dispatch(Action::UnpinAll, Source::Hotkey, &mut host);
+        dispatcher.dispatch(Action::Pin, Source::Hotkey, &mut host);
+        assert!(dispatcher.pinner().is_pinned(a));
+    }
+
+    #[test]
+    fn destroyed_windows_leave_the_registry() {
+        let mut desktop = SimDesktop::new();
+        desktop.open("临时窗口");
+        let mut dispatcher = Dispatcher::new(desktop);
+        let mut host = RecordingHost::default();
+
+        dispatcher.dispatch(Action::Toggle, Source::Hotkey, &mut host);
+        assert_eq!(dispatcher.pinned().len(), 1);
+
+        let window = dispatcher.pinned()[0].window;
+        dispatcher.pinner.backend_mut().close(window);
+        assert!(dispatcher.pinned().is_empty());
+    }
+}
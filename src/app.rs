@@ -0,0 +1,320 @@
+//! 托盘程序的操作处理
+//!
+//! 快捷键、组合键和托盘菜单都通过 [`App::dispatch`] 把动作交给同一个分发器。
+//! `App` 由托盘窗口持有，窗口过程通过窗口用户数据访问它。
+
+use crate::hint::Hint;
+use crate::hotkey::{self, Chords, Hotkeys, Win32Registrar};
+use crate::tray::{self, Notifier};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use window_topmost::chord::{Chord, ChordEvent};
+use window_topmost::hotkeys::Report;
+use window_topmost::platform::windows::{play_beep, Win32Backend};
+use window_topmost::watch::FileWatcher;
+use window_topmost::{
+    Action, ActionHost, Config, Dispatcher, Feedback, HotkeyError, Policy, Settings, Source,
+};
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::WindowsAndMessaging::{KillTimer, PostQuitMessage, SetTimer};
+
+/// 配置文件检查间隔
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 检测到变化后等待写入完成的时间
+const WATCH_SETTLE: Duration = Duration::from_millis(300);
+
+/// 重新注册被占用的快捷键的定时器
+const RETRY_TIMER: usize = 1;
+
+/// 重试间隔（毫秒）
+const RETRY_INTERVAL: u32 = 5000;
+
+/// 组合键等待第二个键超时的定时器
+const CHORD_TIMER: usize = 2;
+
+/// 注册结果中要告诉用户的部分：改用的备选组合、未注册的快捷键和组合键
+fn report_notes(report: &Report, chord_errors: &[HotkeyError]) -> String {
+    let mut notes = String::new();
+    let fallbacks = report.fallback_lines();
+    if !fallbacks.is_empty() {
+        notes.push_str(&format!(
+            "\n\n以下快捷键被占用，已改用备选组合:\n{}",
+            fallbacks
+        ));
+    }
+    if !report.is_ok() {
+        notes.push_str(&format!(
+            "\n\n以下快捷键未注册:\n{}",
+            report.failure_lines()
+        ));
+        if report.blocked() {
+            notes.push_str("\n被占用的快捷键会在释放后自动注册");
+        }
+    }
+    if !chord_errors.is_empty() {
+        let lines: Vec<String> = chord_errors.iter().map(ToString::to_string).collect();
+        notes.push_str(&format!("\n\n以下组合键未注册:\n{}", lines.join("\n")));
+        if chord_errors.iter().any(HotkeyError::is_blocked) {
+            notes.push_str("\n被占用的引导键会在释放后自动注册");
+        }
+    }
+    notes
+}
+
+/// 托盘程序的动作执行环境
+struct TrayHost {
+    config: Config,
+    policy: Policy,
+    hotkeys: Hotkeys,
+    chords: Chords,
+    /// 组合键提示窗口，创建失败时不显示提示
+    hint: Option<Hint>,
+    running: Arc<AtomicBool>,
+    hwnd: HWND,
+}
+
+impl TrayHost {
+    /// 重新加载失败，继续使用原来的配置
+    fn reload_failed(&self, reason: &str) {
+        tray::show_notification(
+            self.hwnd,
+            "配置未生效",
+            &format!("{}\n已继续使用原来的配置", reason),
+        );
+    }
+
+    /// 有被占用的快捷键或引导键时定时重试，没有时停止
+    fn schedule_retry(&self) {
+        let idle = self.hotkeys.pending().is_empty() && self.chords.blocked().next().is_none();
+        unsafe {
+            if idle {
+                let _ = KillTimer(self.hwnd, RETRY_TIMER);
+            } else {
+                SetTimer(self.hwnd, RETRY_TIMER, RETRY_INTERVAL, None);
+            }
+        }
+    }
+
+    /// 按当前配置注册组合键，返回注册失败的原因
+    ///
+    /// 引导键与已注册的快捷键是同一个组合时按重复绑定报告，不参与重试。
+    fn register_chords(&mut self) -> Vec<HotkeyError> {
+        let mut errors = Vec::new();
+        let mut chords = Vec::new();
+        for chord in self
+            .config
+            .chords
+            .iter()
+            .filter_map(|c| Chord::from_config(c).ok())
+        {
+            match self.hotkeys.binding_for(&chord.leader) {
+                Some(binding) => errors.push(HotkeyError::Duplicate {
+                    hotkey: chord.leader.label(),
+                    action: binding.action.name().to_string(),
+                }),
+                None => chords.push(chord),
+            }
+        }
+        errors.extend(self.chords.replace_all(chords));
+        self.sync_chord();
+        errors
+    }
+
+    /// 等待第二个键时显示提示并在截止时间检查超时，不再等待时隐藏
+    fn sync_chord(&self) {
+        let machine = self.chords.machine();
+        unsafe {
+            match machine.deadline() {
+                Some(deadline) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    let ms = u32::try_from(left.as_millis()).unwrap_or(u32::MAX).max(1);
+                    SetTimer(self.hwnd, CHORD_TIMER, ms, None);
+                }
+                None => {
+                    let _ = KillTimer(self.hwnd, CHORD_TIMER);
+                }
+            }
+        }
+        if let Some(hint) = &self.hint {
+            match machine.pending() {
+                Some(chord) => hint.show(&chord.hint()),
+                None => hint.hide(),
+            }
+        }
+    }
+}
+
+impl ActionHost for TrayHost {
+    fn settings(&self) -> &Settings {
+        &self.config.settings
+    }
+
+    fn feedback(&mut self, feedback: Feedback) {
+        if let Some((frequency, duration)) = feedback.beep {
+            play_beep(frequency, duration);
+        }
+        if let Some((title, message)) = feedback.notification {
+            tray::show_notification(self.hwnd, &title, &message);
+        }
+    }
+
+    fn open_config(&mut self) {
+        let config_path = Config::config_path();
+        let _ = std::process::Command::new("notepad.exe")
+            .arg(&config_path)
+            .spawn();
+    }
+
+    /// 重新读取配置文件，全部检查通过后才替换当前配置和快捷键；被占用的快捷键不影响替换
+    fn reload(&mut self) {
+        let layered = match Config::load_layered() {
+            Ok(layered) => layered,
+            Err(err) => return self.reload_failed(&err.to_string()),
+        };
+        let config = layered.config;
+        if let Err(err) = config.validate() {
+            return self.reload_failed(&err.to_string());
+        }
+
+        match self.hotkeys.replace_all(&config.all_bindings()) {
+            Ok(report) => {
+                self.config = config;
+                self.policy = layered.policy;
+                tray::update_tooltip(self.hwnd, &self.config);
+                let chord_errors = self.register_chords();
+                self.schedule_retry();
+                let notes = report_notes(&report, &chord_errors);
+                if self.config.settings.show_notification || !notes.is_empty() {
+                    tray::show_notification(
+                        self.hwnd,
+                        "配置已重新加载",
+                        &format!("快捷键: {}{}", self.config.hotkey.display(), notes),
+                    );
+                }
+            }
+            Err(report) => self.reload_failed(&report.failure_lines()),
+        }
+    }
+
+    fn exit(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        unsafe {
+            PostQuitMessage(0);
+        }
+    }
+}
+
+/// 托盘程序状态
+pub struct App {
+    dispatcher: Dispatcher<Win32Backend>,
+    host: TrayHost,
+}
+
+impl App {
+    /// 创建程序状态并注册快捷键，`hwnd` 为接收快捷键和显示通知的托盘窗口
+    ///
+    /// 快捷键注册失败时照常启动（托盘菜单仍可使用），同时返回要告诉用户的注册结果
+    /// （没有需要说明的为空）；被占用的快捷键在后台定时重试。
+    pub fn new(
+        config: Config,
+        policy: Policy,
+        running: Arc<AtomicBool>,
+        hwnd: HWND,
+    ) -> (Self, String) {
+        let mut hotkeys = Hotkeys::new(Win32Registrar { hwnd });
+        let report = hotkeys.register_all(&config.all_bindings());
+        let mut app = Self {
+            dispatcher: Dispatcher::new(Win32Backend),
+            host: TrayHost {
+                config,
+                policy,
+                hotkeys,
+                chords: Chords::new(Win32Registrar { hwnd }),
+                hint: Hint::create(),
+                running,
+                hwnd,
+            },
+        };
+        let chord_errors = app.host.register_chords();
+        app.host.schedule_retry();
+        (app, report_notes(&report, &chord_errors))
+    }
+
+    /// 管理员策略
+    pub fn policy(&self) -> &Policy {
+        &self.host.policy
+    }
+
+    /// 执行动作；管理员禁止的动作不能从菜单执行
+    pub fn dispatch(&mut self, action: Action, source: Source) {
+        if source == Source::Menu && self.host.policy.forbids_action(action) {
+            return;
+        }
+        self.dispatcher.dispatch(action, source, &mut self.host);
+    }
+
+    /// 处理 WM_TIMER：重试被占用的快捷键和引导键（注册成功时提示），或检查组合键是否超时
+    pub fn on_timer(&mut self, id: usize) {
+        match id {
+            RETRY_TIMER => {
+                let report = self.host.hotkeys.retry();
+                let mut lines = Vec::new();
+                if report.registered() > 0 {
+                    lines.push(report.registered_lines());
+                }
+                for leader in self.host.chords.retry() {
+                    lines.push(format!("{}（组合键）", leader.label()));
+                }
+                if !lines.is_empty() {
+                    tray::show_notification(self.host.hwnd, "快捷键已可用", &lines.join("\n"));
+                }
+                self.host.schedule_retry();
+            }
+            CHORD_TIMER => {
+                // 定时器可能略早于截止时间触发，这时 sync_chord 按剩余时间重新设置
+                self.host.chords.tick(Instant::now());
+                self.host.sync_chord();
+            }
+            _ => {}
+        }
+    }
+
+    /// 处理 WM_HOTKEY：先交给组合键，再查普通快捷键
+    pub fn on_hotkey(&mut self, id: i32) {
+        let event = self
+            .host
+            .chords
+            .on_hotkey(id, Instant::now(), hotkey::sides_down);
+        if let Some(event) = event {
+            self.host.sync_chord();
+            if let ChordEvent::Fired(action) = event {
+                self.dispatch(action, Source::Hotkey);
+            }
+            return;
+        }
+        if let Some(action) = self.host.hotkeys.action(id, hotkey::sides_down) {
+            self.dispatch(action, Source::Hotkey);
+        }
+    }
+}
+
+/// 在后台线程监视参与合并的配置文件，任何一个变化时让托盘线程重新加载
+pub fn spawn_config_watcher(notifier: Notifier, files: Vec<PathBuf>) {
+    let mut watchers: Vec<FileWatcher> = files.into_iter().map(FileWatcher::new).collect();
+    // 每个都要检查，以便记下各自的最新状态
+    let changed = |watchers: &mut Vec<FileWatcher>| {
+        watchers.iter_mut().fold(false, |any, w| w.changed() | any)
+    };
+    std::thread::spawn(move || loop {
+        std::thread::sleep(WATCH_INTERVAL);
+        if changed(&mut watchers) {
+            // 编辑器可能分几次写入，等文件稳定后再加载
+            std::thread::sleep(WATCH_SETTLE);
+            changed(&mut watchers);
+            notifier.dispatch(Action::Reload, Source::External);
+        }
+    });
+}
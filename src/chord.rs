@@ -0,0 +1,486 @@
+//! 组合键
+//!
+//! 先按引导键（如 Ctrl+K），再在限定时间内按第二个键（如 T），由第二个键决定执行的动作。
+//! [`ChordMachine`] 只记录等待状态，时间由调用方传入，与平台无关；
+//! [`ChordKeys`] 通过 [`Registrar`] 注册引导键，只在等待第二个键时注册第二个键和 Esc。
+
+use crate::accelerator::{Accelerator, Sides};
+use crate::action::Action;
+use crate::config::ChordConfig;
+use crate::error::HotkeyError;
+use crate::hotkeys::Registrar;
+use crate::keys::Key;
+use std::time::{Duration, Instant};
+
+/// 引导键的 ID 从这里开始（普通快捷键使用更小的 ID）
+pub const LEADER_IDS: i32 = 0xA000;
+
+/// 第二个键的 ID 从这里开始
+const STEP_IDS: i32 = 0xB000;
+
+/// 解析后的组合键
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chord {
+    /// 引导键
+    pub leader: Accelerator,
+    /// 第二个键及其动作
+    pub keys: Vec<(Accelerator, Action)>,
+    /// 等待第二个键的时间
+    pub timeout: Duration,
+}
+
+impl Chord {
+    /// 从配置解析；引导键只使用首选组合
+    pub fn from_config(config: &ChordConfig) -> Result<Self, HotkeyError> {
+        Ok(Self {
+            leader: config.leader.accelerator()?,
+            keys: config.keys()?,
+            timeout: Duration::from_millis(config.timeout),
+        })
+    }
+
+    /// 第二个键对应的动作
+    pub fn action(&self, key: &Accelerator) -> Option<Action> {
+        self.keys
+            .iter()
+            .find(|(k, _)| k.overlaps(key))
+            .map(|(_, action)| *action)
+    }
+
+    /// 等待第二个键时显示的提示，如 `Ctrl+K …` 下面每行一个 `T  切换置顶`
+    pub fn hint(&self) -> String {
+        let mut lines = vec![format!("{} …", self.leader.label())];
+        for (key, action) in &self.keys {
+            lines.push(format!("{}  {}", key.label(), action.label()));
+        }
+        lines.push("Esc  取消".to_string());
+        lines.join("\n")
+    }
+}
+
+/// 组合键状态的变化
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChordEvent {
+    /// 按下了第几个组合键的引导键，开始等待第二个键
+    Started(usize),
+    /// 按下了第二个键，执行动作
+    Fired(Action),
+    /// 按了 Esc 或其他不属于这个组合键的键
+    Cancelled,
+    /// 超时没有按第二个键
+    Expired,
+}
+
+/// 组合键状态机
+#[derive(Debug, Default)]
+pub struct ChordMachine {
+    chords: Vec<Chord>,
+    /// 等待中的组合键下标和截止时间
+    pending: Option<(usize, Instant)>,
+}
+
+impl ChordMachine {
+    pub fn new(chords: Vec<Chord>) -> Self {
+        Self {
+            chords,
+            pending: None,
+        }
+    }
+
+    /// 全部组合键
+    pub fn chords(&self) -> &[Chord] {
+        &self.chords
+    }
+
+    /// 正在等待第二个键的组合键
+    pub fn pending(&self) -> Option<&Chord> {
+        self.pending.map(|(index, _)| &self.chords[index])
+    }
+
+    /// 等待第二个键的截止时间
+    pub fn deadline(&self) -> Option<Instant> {
+        self.pending.map(|(_, deadline)| deadline)
+    }
+
+    /// 按下了第 `index` 个组合键的引导键
+    ///
+    /// 等待中的组合键把这个组合当作第二个键时（如 Ctrl+K 再按 Ctrl+K）按第二个键处理；
+    /// 否则重新开始等待。
+    pub fn leader(&mut self, index: usize, now: Instant) -> Option<ChordEvent> {
+        let leader = self.chords.get(index)?.leader;
+        let is_step = self
+            .pending()
+            .is_some_and(|chord| chord.action(&leader).is_some());
+        if is_step && self.tick(now).is_none() {
+            return self.key(&leader, now);
+        }
+        self.pending = Some((index, now + self.chords[index].timeout));
+        Some(ChordEvent::Started(index))
+    }
+
+    /// 等待中按下了一个键；没有在等待时返回 `None`
+    pub fn key(&mut self, key: &Accelerator, now: Instant) -> Option<ChordEvent> {
+        let (index, deadline) = self.pending.take()?;
+        if now >= deadline {
+            return Some(ChordEvent::Expired);
+        }
+        Some(match self.chords[index].action(key) {
+            Some(action) => ChordEvent::Fired(action),
+            None => ChordEvent::Cancelled,
+        })
+    }
+
+    /// 检查是否超时
+    pub fn tick(&mut self, now: Instant) -> Option<ChordEvent> {
+        let (_, deadline) = self.pending?;
+        if now < deadline {
+            return None;
+        }
+        self.pending = None;
+        Some(ChordEvent::Expired)
+    }
+
+    /// 放弃等待
+    pub fn cancel(&mut self) -> Option<ChordEvent> {
+        self.pending.take().map(|_| ChordEvent::Cancelled)
+    }
+}
+
+/// 组合键的系统注册，销毁时注销全部
+pub struct ChordKeys<R: Registrar> {
+    registrar: R,
+    machine: ChordMachine,
+    /// 已注册的引导键（组合键下标）
+    leaders: Vec<usize>,
+    /// 被其他程序占用、等待重试的引导键（组合键下标）
+    blocked: Vec<usize>,
+    /// 等待中注册的第二个键
+    steps: Vec<(i32, Accelerator)>,
+}
+
+impl<R: Registrar> ChordKeys<R> {
+    pub fn new(registrar: R) -> Self {
+        Self {
+            registrar,
+            machine: ChordMachine::default(),
+            leaders: Vec::new(),
+            blocked: Vec::new(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// 状态机
+    pub fn machine(&self) -> &ChordMachine {
+        &self.machine
+    }
+
+    /// 注销原来的组合键，注册新的一组引导键，返回注册失败的原因
+    ///
+    /// 被占用的引导键记下来，由 [`retry`](Self::retry) 重试。
+    pub fn replace_all(&mut self, chords: Vec<Chord>) -> Vec<HotkeyError> {
+        self.clear();
+        self.machine = ChordMachine::new(chords);
+        let mut errors = Vec::new();
+        for (index, chord) in self.machine.chords.iter().enumerate() {
+            match register(
+                &mut self.registrar,
+                LEADER_IDS + index as i32,
+                &chord.leader,
+            ) {
+                Ok(()) => self.leaders.push(index),
+                Err(err) => {
+                    if err.is_blocked() {
+                        self.blocked.push(index);
+                    }
+                    errors.push(err);
+                }
+            }
+        }
+        errors
+    }
+
+    /// 重新注册被占用的引导键，返回这次注册成功的；仍被占用的继续等待
+    pub fn retry(&mut self) -> Vec<Accelerator> {
+        let mut registered = Vec::new();
+        for index in std::mem::take(&mut self.blocked) {
+            let leader = self.machine.chords[index].leader;
+            match register(&mut self.registrar, LEADER_IDS + index as i32, &leader) {
+                Ok(()) => {
+                    self.leaders.push(index);
+                    registered.push(leader);
+                }
+                Err(err) if err.is_blocked() => self.blocked.push(index),
+                Err(_) => {}
+            }
+        }
+        registered
+    }
+
+    /// 等待重试的引导键
+    pub fn blocked(&self) -> impl Iterator<Item = &Accelerator> {
+        self.blocked
+            .iter()
+            .map(|&index| &self.machine.chords[index].leader)
+    }
+
+    /// 收到 WM_HOTKEY；不是组合键的 ID 返回 `None`
+    ///
+    /// `pressed` 检查限定左右的修饰键是否按在对应的一侧。
+    pub fn on_hotkey(
+        &mut self,
+        id: i32,
+        now: Instant,
+        pressed: impl Fn(&Sides) -> bool,
+    ) -> Option<ChordEvent> {
+        let event = if let Some(&(_, key)) = self.steps.iter().find(|(s, _)| *s == id) {
+            self.machine.key(&key, now)
+        } else {
+            let index = usize::try_from(id - LEADER_IDS).ok()?;
+            let chord = self.machine.chords.get(index)?;
+            if !self.leaders.contains(&index) || !pressed(&chord.leader.sides) {
+                return None;
+            }
+            self.machine.leader(index, now)
+        };
+        self.sync();
+        event
+    }
+
+    /// 检查是否超时
+    pub fn tick(&mut self, now: Instant) -> Option<ChordEvent> {
+        let event = self.machine.tick(now);
+        self.sync();
+        event
+    }
+
+    /// 放弃等待
+    pub fn cancel(&mut self) -> Option<ChordEvent> {
+        let event = self.machine.cancel();
+        self.sync();
+        event
+    }
+
+    /// 等待第二个键时注册第二个键和 Esc，不再等待时注销
+    fn sync(&mut self) {
+        for (id, _) in self.steps.drain(..) {
+            self.registrar.unregister(id);
+        }
+        let Some(chord) = self.machine.pending() else {
+            return;
+        };
+        let escape = Accelerator::new(Key::Escape);
+        let keys = chord.keys.iter().map(|(key, _)| *key).chain([escape]);
+        for (id, key) in (STEP_IDS..).zip(keys) {
+            // 与引导键相同的组合已经注册，由引导键的 ID 触发；被占用的键跳过
+            let is_leader = self.machine.chords.iter().any(|c| c.leader.overlaps(&key));
+            if !is_leader && register(&mut self.registrar, id, &key).is_ok() {
+                self.steps.push((id, key));
+            }
+        }
+    }
+
+    /// 注销全部组合键
+    pub fn clear(&mut self) {
+        self.machine.cancel();
+        self.sync();
+        for index in self.leaders.drain(..) {
+            self.registrar.unregister(LEADER_IDS + index as i32);
+        }
+        self.blocked.clear();
+    }
+}
+
+impl<R: Registrar> Drop for ChordKeys<R> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+fn register<R: Registrar>(
+    registrar: &mut R,
+    id: i32,
+    accel: &Accelerator,
+) -> Result<(), HotkeyError> {
+    match accel.key.vk_code() {
+        0 => Err(HotkeyError::InvalidKey {
+            key: accel.key.name(),
+        }),
+        vk => registrar.register(id, accel, vk),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn accel(s: &str) -> Accelerator {
+        s.parse().unwrap()
+    }
+
+    fn chord(leader: &str, keys: &[(&str, Action)]) -> Chord {
+        let config = ChordConfig {
+            leader: accel(leader).into(),
+            timeout: 1000,
+            keys: keys
+                .iter()
+                .map(|(key, action)| (key.to_string(), *action))
+                .collect(),
+        };
+        Chord::from_config(&config).unwrap()
+    }
+
+    fn machine() -> ChordMachine {
+        ChordMachine::new(vec![
+            chord("Ctrl+K", &[("T", Action::Toggle), ("U", Action::UnpinAll)]),
+            chord("Ctrl+J", &[("Ctrl+J", Action::Reload)]),
+        ])
+    }
+
+    fn ms(start: Instant, ms: u64) -> Instant {
+        start + Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn second_key_fires_its_action() {
+        let mut machine = machine();
+        let start = Instant::now();
+        assert_eq!(machine.key(&accel("T"), start), None);
+        assert_eq!(machine.leader(0, start), Some(ChordEvent::Started(0)));
+        assert_eq!(machine.deadline(), Some(ms(start, 1000)));
+        assert_eq!(
+            machine.key(&accel("U"), ms(start, 500)),
+            Some(ChordEvent::Fired(Action::UnpinAll))
+        );
+        assert!(machine.pending().is_none());
+    }
+
+    #[test]
+    fn unknown_key_cancels_and_timeout_expires() {
+        let mut machine = machine();
+        let start = Instant::now();
+        machine.leader(0, start);
+        assert_eq!(
+            machine.key(&accel("Escape"), ms(start, 10)),
+            Some(ChordEvent::Cancelled)
+        );
+
+        machine.leader(0, start);
+        assert_eq!(machine.tick(ms(start, 999)), None);
+        assert_eq!(machine.tick(ms(start, 1000)), Some(ChordEvent::Expired));
+        assert_eq!(machine.tick(ms(start, 1001)), None);
+
+        // 超时后才到达的键不执行
+        machine.leader(0, start);
+        assert_eq!(
+            machine.key(&accel("T"), ms(start, 1200)),
+            Some(ChordEvent::Expired)
+        );
+    }
+
+    #[test]
+    fn leaders_restart_or_complete_a_chord() {
+        let mut machine = machine();
+        let start = Instant::now();
+        machine.leader(0, start);
+        // 另一个引导键重新开始等待
+        assert_eq!(
+            machine.leader(1, ms(start, 900)),
+            Some(ChordEvent::Started(1))
+        );
+        assert_eq!(machine.deadline(), Some(ms(start, 1900)));
+        // 第二个键与引导键相同
+        assert_eq!(
+            machine.leader(1, ms(start, 1000)),
+            Some(ChordEvent::Fired(Action::Reload))
+        );
+        assert_eq!(machine.leader(5, start), None);
+    }
+
+    #[test]
+    fn hint_lists_second_keys() {
+        // 按书写顺序列出
+        let chord = chord("Ctrl+K", &[("U", Action::UnpinAll), ("T", Action::Toggle)]);
+        assert_eq!(
+            chord.hint(),
+            "Ctrl+K …\nU  取消全部置顶\nT  切换置顶\nEsc  取消"
+        );
+    }
+
+    /// 记录当前注册的 ID；第二个列表中的 ID 被其他程序占用
+    #[derive(Clone, Default)]
+    struct Recorder(Rc<RefCell<Vec<i32>>>, Rc<RefCell<Vec<i32>>>);
+
+    impl Registrar for Recorder {
+        fn register(&mut self, id: i32, accel: &Accelerator, _: u32) -> Result<(), HotkeyError> {
+            if self.1.borrow().contains(&id) {
+                return Err(HotkeyError::Conflict {
+                    hotkey: accel.label(),
+                });
+            }
+            self.0.borrow_mut().push(id);
+            Ok(())
+        }
+
+        fn unregister(&mut self, id: i32) {
+            self.0.borrow_mut().retain(|r| *r != id);
+        }
+    }
+
+    #[test]
+    fn second_keys_are_registered_only_while_pending() {
+        let recorder = Recorder::default();
+        let mut keys = ChordKeys::new(recorder.clone());
+        let errors = keys.replace_all(machine().chords);
+        assert!(errors.is_empty());
+        assert_eq!(*recorder.0.borrow(), [LEADER_IDS, LEADER_IDS + 1]);
+
+        let start = Instant::now();
+        let any = |_: &Sides| true;
+        assert_eq!(keys.on_hotkey(1, start, any), None);
+        assert_eq!(
+            keys.on_hotkey(LEADER_IDS, start, any),
+            Some(ChordEvent::Started(0))
+        );
+        // T、U 和 Esc
+        assert_eq!(recorder.0.borrow().len(), 5);
+        assert_eq!(
+            keys.on_hotkey(STEP_IDS + 1, start, any),
+            Some(ChordEvent::Fired(Action::UnpinAll))
+        );
+        assert_eq!(recorder.0.borrow().len(), 2);
+
+        // Ctrl+J 的第二个键就是引导键，不另外注册
+        keys.on_hotkey(LEADER_IDS + 1, start, any);
+        assert_eq!(recorder.0.borrow().len(), 3);
+
+        drop(keys);
+        assert!(recorder.0.borrow().is_empty());
+    }
+
+    #[test]
+    fn blocked_leaders_are_retried() {
+        let recorder = Recorder::default();
+        recorder.1.borrow_mut().push(LEADER_IDS + 1);
+        let mut keys = ChordKeys::new(recorder.clone());
+        let errors = keys.replace_all(machine().chords);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].is_blocked());
+        assert_eq!(keys.blocked().collect::<Vec<_>>(), [&accel("Ctrl+J")]);
+
+        // 还被占用时继续等待
+        assert!(keys.retry().is_empty());
+        assert_eq!(keys.blocked().count(), 1);
+
+        recorder.1.borrow_mut().clear();
+        assert_eq!(keys.retry(), [accel("Ctrl+J")]);
+        assert_eq!(keys.blocked().count(), 0);
+        assert_eq!(*recorder.0.borrow(), [LEADER_IDS, LEADER_IDS + 1]);
+        let any = |_: &Sides| true;
+        assert_eq!(
+            keys.on_hotkey(LEADER_IDS + 1, Instant::now(), any),
+            Some(ChordEvent::Started(1))
+        );
+    }
+}
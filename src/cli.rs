@@ -0,0 +1,267 @@
+//! 命令行子命令
+//!
+//! `window-topmost check-config [路径]`：检查配置文件并列出错误和警告。
+//! `window-topmost config ...`：查看和修改配置，导出 JSON Schema。
+//! `--config <路径>` 可以放在任何子命令前，指定要使用的配置文件。
+
+use std::path::PathBuf;
+use window_topmost::{diagnostics, keypath, location, schema};
+use window_topmost::{Config, LayerKind, Layered, Policy};
+
+/// 用法说明
+const USAGE: &str = "用法:
+  window-topmost [--config 路径]                       启动程序
+  window-topmost [--config 路径] check-config [路径]   检查配置文件
+  window-topmost [--config 路径] config show           显示配置文件的内容
+  window-topmost [--config 路径] config show --effective
+                                                       显示生效的配置及来源
+  window-topmost [--config 路径] config get 配置项      显示配置项的值，如 hotkey.key
+  window-topmost [--config 路径] config set 配置项 值   修改配置文件，如 settings.play_sound false
+  window-topmost config schema                         输出配置文件的 JSON Schema
+
+配置文件位置依次为: --config、环境变量 WINDOW_TOPMOST_CONFIG、
+程序目录（有 portable.txt 或 config.toml 时）、用户配置目录";
+
+/// 执行命令行参数指定的子命令，返回退出码；没有子命令时返回 None
+///
+/// 同时根据 `--config` 确定配置文件位置。
+pub fn run(args: &[String]) -> Option<i32> {
+    let (flag, args) = match split_config_flag(args) {
+        Ok(split) => split,
+        Err(message) => {
+            #[cfg(windows)]
+            attach_console();
+            eprintln!("{}\n\n{}", message, USAGE);
+            return Some(2);
+        }
+    };
+    location::init(flag.as_deref());
+
+    let command = args.first()?;
+
+    #[cfg(windows)]
+    attach_console();
+
+    Some(match command.as_str() {
+        "check-config" => check_config(args.get(1).map(PathBuf::from)),
+        "config" => config(&args[1..]),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            0
+        }
+        other => {
+            eprintln!("未知的命令: {}\n\n{}", other, USAGE);
+            2
+        }
+    })
+}
+
+/// 取出 `--config <路径>` 或 `--config=<路径>`，返回其余的参数
+fn split_config_flag(args: &[String]) -> Result<(Option<PathBuf>, Vec<String>), String> {
+    let mut flag = None;
+    let mut rest = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--config" {
+            let path = iter.next().ok_or("--config 缺少配置文件路径")?;
+            flag = Some(PathBuf::from(path));
+        } else if let Some(path) = arg.strip_prefix("--config=") {
+            flag = Some(PathBuf::from(path));
+        } else {
+            rest.push(arg.clone());
+        }
+    }
+    Ok((flag, rest))
+}
+
+/// 检查配置文件，有错误时返回 1
+fn check_config(path: Option<PathBuf>) -> i32 {
+    let path = path.unwrap_or_else(|| {
+        println!("配置文件: {}", location::current());
+        Config::config_path()
+    });
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) => {
+            eprintln!("无法读取配置文件 {}: {}", path.display(), err);
+            return 2;
+        }
+    };
+
+    let mut report = diagnostics::check(&content, &path);
+    match location::policy_file()
+        .map(|p| Policy::load(&p))
+        .transpose()
+    {
+        Ok(policy) => {
+            let policy = policy.unwrap_or_default();
+            if report.errors() == 0 {
+                report.diagnostics.extend(policy.check(&content, &path));
+            }
+        }
+        Err(err) => eprintln!("{}", err),
+    }
+    for diagnostic in &report.diagnostics {
+        println!("{}", diagnostic);
+    }
+
+    if report.diagnostics.is_empty() {
+        println!("{}: 没有发现问题", path.display());
+    } else {
+        println!(
+            "{}: {} 个错误，{} 个警告",
+            path.display(),
+            report.errors(),
+            report.warnings()
+        );
+    }
+    i32::from(report.errors() > 0)
+}
+
+/// `config` 子命令
+fn config(args: &[String]) -> i32 {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["show"] => show_file(),
+        ["show", "--effective"] => show_effective(),
+        ["get", key] => get(key),
+        ["set", key, value] => set(key, value),
+        ["schema"] => {
+            let schema = schema::config_schema();
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&schema).expect("JSON 总能序列化")
+            );
+            0
+        }
+        _ => {
+            eprintln!("无效的 config 命令\n\n{}", USAGE);
+            2
+        }
+    }
+}
+
+/// 读取合并后的配置，失败时输出错误
+fn load() -> Option<Layered> {
+    Config::load_layered()
+        .map_err(|err| eprintln!("{}", err))
+        .ok()
+}
+
+/// 显示配置文件原本的内容
+fn show_file() -> i32 {
+    // 第一次运行时先创建配置文件
+    if load().is_none() {
+        return 1;
+    }
+    let path = Config::config_path();
+    match std::fs::read_to_string(&path) {
+        Ok(content) => {
+            println!("# {}", location::current());
+            print!("{}", content);
+            0
+        }
+        Err(err) => {
+            eprintln!("无法读取配置文件 {}: {}", path.display(), err);
+            1
+        }
+    }
+}
+
+/// 显示生效的配置项的值
+fn get(key: &str) -> i32 {
+    let Some(layered) = load() else {
+        return 1;
+    };
+    match keypath::get(&layered.config, key) {
+        Ok(value) => {
+            println!("{}", keypath::format(&value));
+            0
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            1
+        }
+    }
+}
+
+/// 修改配置文件中的一项
+fn set(key: &str, value: &str) -> i32 {
+    let Some(layered) = load() else {
+        return 1;
+    };
+    let path = Config::config_path();
+    if let Err(err) = keypath::set_in_file(&path, key, value, &layered) {
+        eprintln!("{}", err);
+        return 1;
+    }
+    // 其他层（如策略）可能覆盖刚写入的值
+    match load().map(|layered| keypath::get(&layered.config, key)) {
+        Some(Ok(value)) => {
+            println!(
+                "{} = {}  # {}",
+                key,
+                keypath::format(&value),
+                path.display()
+            );
+            0
+        }
+        Some(Err(err)) => {
+            eprintln!("{}", err);
+            1
+        }
+        None => 1,
+    }
+}
+
+/// 显示合并后的配置，每行注明来源
+fn show_effective() -> i32 {
+    let Some(layered) = load() else {
+        return 1;
+    };
+
+    println!("配置文件: {}", location::current());
+    println!("合并顺序（后面的覆盖前面的）:");
+    for (i, layer) in layered.layers.iter().enumerate() {
+        println!("  {}. {}", i + 1, layer);
+    }
+    println!();
+
+    let entries = layered.entries();
+    let lines: Vec<String> = entries
+        .iter()
+        .map(|(key, value, _)| format!("{} = {}", key, value))
+        .collect();
+    let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    for (line, (_, _, layer)) in lines.iter().zip(&entries) {
+        let locked = if layer.kind == LayerKind::Policy {
+            "（已锁定）"
+        } else {
+            ""
+        };
+        println!("{:<width$}  # {}{}", line, layer, locked, width = width);
+    }
+
+    let policy = &layered.policy;
+    if !policy.forbidden_hotkeys().is_empty() || !policy.forbidden_actions().is_empty() {
+        println!();
+        println!("管理员禁止的快捷键和动作:");
+        for accel in policy.forbidden_hotkeys() {
+            println!("  快捷键 {}", accel);
+        }
+        for action in policy.forbidden_actions() {
+            println!("  动作 {}", action.name());
+        }
+    }
+    0
+}
+
+/// 窗口程序没有控制台，从命令行启动时使用父进程的控制台输出
+#[cfg(windows)]
+fn attach_console() {
+    use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+
+    unsafe {
+        let _ = AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
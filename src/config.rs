@@ -1,27 +1,42 @@
 //! 配置文件管理模块
 
-use anyhow::{Context, Result};
-use serde::{Deserialize, Serialize};
+use crate::accelerator::{Accelerator, Side, Sides};
+use crate::action::Action;
+use crate::diagnostics;
+use crate::edit;
+use crate::error::{ConfigError, HotkeyError};
+use crate::keys::{self, Key, MOD_ALT, MOD_CONTROL, MOD_NOREPEAT, MOD_SHIFT, MOD_WIN};
+use crate::layers::{self, Layered};
+use crate::location;
+use crate::migrate::{self, CURRENT_VERSION};
+use crate::policy::Policy;
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// 快捷键配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// 除了分开写的修饰键，也可以直接写成字符串：`hotkey = "Ctrl+Alt+T"`；
+/// 写成字符串数组时第一个为首选组合，其余为备选：`hotkey = ["Ctrl+Space", "Ctrl+Alt+Space"]`。
+#[derive(Debug, Clone)]
 pub struct HotkeyConfig {
     /// Ctrl 键
-    #[serde(default)]
     pub ctrl: bool,
     /// Alt 键
-    #[serde(default)]
     pub alt: bool,
     /// Shift 键
-    #[serde(default)]
     pub shift: bool,
     /// Win 键
-    #[serde(default)]
     pub win: bool,
+    /// 限定左右的修饰键，表格写法为 `ctrl = "left"`
+    pub sides: Sides,
     /// 主键 (如 "Space", "T", "F9" 等)
     pub key: String,
+    /// 备选组合，首选组合被占用时依次尝试
+    pub fallbacks: Vec<Accelerator>,
 }
 
 impl Default for HotkeyConfig {
@@ -31,116 +46,235 @@ impl Default for HotkeyConfig {
             alt: false,
             shift: false,
             win: false,
+            sides: Sides::default(),
             key: "Space".to_string(),
+            fallbacks: Vec::new(),
         }
     }
 }
 
-impl HotkeyConfig {
-    /// 获取显示用的快捷键字符串
-    pub fn display(&self) -> String {
-        let mut parts = Vec::new();
+/// 表格写法中的 Ctrl、Alt、Shift：`true`、`false`，或限定一侧的 `"left"`、`"right"`
+#[derive(Debug, Clone, Copy, Default)]
+struct Flag(bool, Side);
 
-        if self.ctrl {
-            parts.push("Ctrl");
+impl Serialize for Flag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.1.name() {
+            Some(side) if self.0 => serializer.serialize_str(side),
+            _ => serializer.serialize_bool(self.0),
         }
-        if self.alt {
-            parts.push("Alt");
+    }
+}
+
+impl<'de> Deserialize<'de> for Flag {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FlagVisitor;
+
+        impl<'de> Visitor<'de> for FlagVisitor {
+            type Value = Flag;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("true、false、\"left\" 或 \"right\"")
+            }
+
+            fn visit_bool<E: de::Error>(self, on: bool) -> Result<Self::Value, E> {
+                Ok(Flag(on, Side::Either))
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<Self::Value, E> {
+                Side::from_name(s)
+                    .map(|side| Flag(true, side))
+                    .ok_or_else(|| E::invalid_value(de::Unexpected::Str(s), &self))
+            }
         }
-        if self.shift {
-            parts.push("Shift");
+
+        deserializer.deserialize_any(FlagVisitor)
+    }
+}
+
+/// 表格写法，字段与 [`HotkeyConfig`] 相同
+#[derive(Deserialize)]
+struct HotkeyFields {
+    #[serde(default)]
+    ctrl: Flag,
+    #[serde(default)]
+    alt: Flag,
+    #[serde(default)]
+    shift: Flag,
+    #[serde(default)]
+    win: bool,
+    key: String,
+}
+
+/// 序列化为表格写法，有备选组合时为字符串数组
+impl Serialize for HotkeyConfig {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if !self.fallbacks.is_empty() {
+            return serializer.collect_seq(self.candidate_names());
         }
-        if self.win {
-            parts.push("Win");
+        let mut table = serializer.serialize_struct("HotkeyConfig", 5)?;
+        table.serialize_field("ctrl", &Flag(self.ctrl, self.sides.ctrl))?;
+        table.serialize_field("alt", &Flag(self.alt, self.sides.alt))?;
+        table.serialize_field("shift", &Flag(self.shift, self.sides.shift))?;
+        table.serialize_field("win", &self.win)?;
+        table.serialize_field("key", &self.key)?;
+        table.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for HotkeyConfig {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct HotkeyVisitor;
+
+        impl<'de> Visitor<'de> for HotkeyVisitor {
+            type Value = HotkeyConfig;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("快捷键字符串（如 \"Ctrl+Alt+T\"）、字符串数组或 [hotkey] 表")
+            }
+
+            /// 首选组合和备选组合
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut candidates = Vec::new();
+                while let Some(text) = seq.next_element::<String>()? {
+                    candidates.push(text.parse::<Accelerator>().map_err(de::Error::custom)?);
+                }
+                let mut candidates = candidates.into_iter();
+                let first = candidates
+                    .next()
+                    .ok_or_else(|| de::Error::invalid_length(0, &"至少一个快捷键"))?;
+                Ok(HotkeyConfig {
+                    fallbacks: candidates.collect(),
+                    ..first.into()
+                })
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<Self::Value, E> {
+                s.parse::<Accelerator>().map(Into::into).map_err(E::custom)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                let f = HotkeyFields::deserialize(de::value::MapAccessDeserializer::new(map))?;
+                Ok(HotkeyConfig {
+                    ctrl: f.ctrl.0,
+                    alt: f.alt.0,
+                    shift: f.shift.0,
+                    win: f.win,
+                    sides: Sides {
+                        ctrl: f.ctrl.1,
+                        alt: f.alt.1,
+                        shift: f.shift.1,
+                    },
+                    key: f.key,
+                    fallbacks: Vec::new(),
+                })
+            }
         }
-        parts.push(&self.key);
 
-        parts.join(" + ")
+        deserializer.deserialize_any(HotkeyVisitor)
     }
+}
 
-    /// 将按键名称转换为虚拟键码
-    pub fn get_vk_code(&self) -> Option<u32> {
-        use windows::Win32::UI::Input::KeyboardAndMouse::*;
-
-        let key_upper = self.key.to_uppercase();
-
-        Some(match key_upper.as_str() {
-            // 功能键
-            "F1" => VK_F1.0 as u32,
-            "F2" => VK_F2.0 as u32,
-            "F3" => VK_F3.0 as u32,
-            "F4" => VK_F4.0 as u32,
-            "F5" => VK_F5.0 as u32,
-            "F6" => VK_F6.0 as u32,
-            "F7" => VK_F7.0 as u32,
-            "F8" => VK_F8.0 as u32,
-            "F9" => VK_F9.0 as u32,
-            "F10" => VK_F10.0 as u32,
-            "F11" => VK_F11.0 as u32,
-            "F12" => VK_F12.0 as u32,
-
-            // 特殊键
-            "SPACE" => VK_SPACE.0 as u32,
-            "ENTER" | "RETURN" => VK_RETURN.0 as u32,
-            "TAB" => VK_TAB.0 as u32,
-            "ESCAPE" | "ESC" => VK_ESCAPE.0 as u32,
-            "BACKSPACE" => VK_BACK.0 as u32,
-            "DELETE" | "DEL" => VK_DELETE.0 as u32,
-            "INSERT" | "INS" => VK_INSERT.0 as u32,
-            "HOME" => VK_HOME.0 as u32,
-            "END" => VK_END.0 as u32,
-            "PAGEUP" | "PGUP" => VK_PRIOR.0 as u32,
-            "PAGEDOWN" | "PGDN" => VK_NEXT.0 as u32,
-
-            // 方向键
-            "UP" => VK_UP.0 as u32,
-            "DOWN" => VK_DOWN.0 as u32,
-            "LEFT" => VK_LEFT.0 as u32,
-            "RIGHT" => VK_RIGHT.0 as u32,
-
-            // 数字键盘
-            "NUMPAD0" | "NUM0" => VK_NUMPAD0.0 as u32,
-            "NUMPAD1" | "NUM1" => VK_NUMPAD1.0 as u32,
-            "NUMPAD2" | "NUM2" => VK_NUMPAD2.0 as u32,
-            "NUMPAD3" | "NUM3" => VK_NUMPAD3.0 as u32,
-            "NUMPAD4" | "NUM4" => VK_NUMPAD4.0 as u32,
-            "NUMPAD5" | "NUM5" => VK_NUMPAD5.0 as u32,
-            "NUMPAD6" | "NUM6" => VK_NUMPAD6.0 as u32,
-            "NUMPAD7" | "NUM7" => VK_NUMPAD7.0 as u32,
-            "NUMPAD8" | "NUM8" => VK_NUMPAD8.0 as u32,
-            "NUMPAD9" | "NUM9" => VK_NUMPAD9.0 as u32,
-
-            // 字母键 (A-Z)
-            s if s.len() == 1 && s.chars().next().unwrap().is_ascii_alphabetic() => {
-                s.chars().next().unwrap() as u32
-            }
+impl From<Accelerator> for HotkeyConfig {
+    fn from(accel: Accelerator) -> Self {
+        Self {
+            ctrl: accel.ctrl,
+            alt: accel.alt,
+            shift: accel.shift,
+            win: accel.win,
+            sides: accel.sides,
+            key: accel.key.name(),
+            fallbacks: Vec::new(),
+        }
+    }
+}
+
+impl HotkeyConfig {
+    /// 获取显示用的快捷键字符串，扫描码按键显示为当前键盘布局上的名称
+    pub fn display(&self) -> String {
+        match self.accelerator() {
+            Ok(accel) => accel.label(),
+            Err(_) => self.canonical(),
+        }
+    }
 
-            // 数字键 (0-9)
-            s if s.len() == 1 && s.chars().next().unwrap().is_ascii_digit() => {
-                s.chars().next().unwrap() as u32
+    /// 写入配置文件的规范写法（可以再被解析，如 "Ctrl+Alt+T"）
+    pub fn canonical(&self) -> String {
+        match self.accelerator() {
+            Ok(accel) => accel.to_string(),
+            Err(_) => {
+                let mut parts = Vec::new();
+                for (on, side, name) in [
+                    (self.ctrl, self.sides.ctrl, "Ctrl"),
+                    (self.alt, self.sides.alt, "Alt"),
+                    (self.shift, self.sides.shift, "Shift"),
+                    (self.win, Side::Either, "Win"),
+                ] {
+                    if on {
+                        parts.push(format!("{}{}", side.prefix(), name));
+                    }
+                }
+                parts.push(self.key.clone());
+                parts.join("+")
             }
+        }
+    }
+
+    /// 首选组合在前的全部候选组合
+    pub fn candidates(&self) -> Result<Vec<Accelerator>, HotkeyError> {
+        let first = self.accelerator()?;
+        Ok(std::iter::once(first)
+            .chain(self.fallbacks.iter().copied())
+            .collect())
+    }
+
+    /// 全部候选组合的规范写法
+    pub(crate) fn candidate_names(&self) -> Vec<String> {
+        std::iter::once(self.canonical())
+            .chain(self.fallbacks.iter().map(Accelerator::to_string))
+            .collect()
+    }
+
+    /// 写入配置文件的值：没有备选组合时为字符串，否则为字符串数组
+    pub fn to_toml(&self) -> toml::Value {
+        if self.fallbacks.is_empty() {
+            self.canonical().into()
+        } else {
+            self.candidate_names().into()
+        }
+    }
+
+    /// 转换为快捷键组合
+    pub fn accelerator(&self) -> Result<Accelerator, HotkeyError> {
+        Ok(Accelerator {
+            ctrl: self.ctrl,
+            alt: self.alt,
+            shift: self.shift,
+            win: self.win,
+            sides: self.sides,
+            key: self.parse_key()?,
+        })
+    }
+
+    /// 解析主键
+    pub fn get_key(&self) -> Option<Key> {
+        Key::from_name(&self.key)
+    }
 
-            // 符号键
-            "`" | "~" => VK_OEM_3.0 as u32,
-            "-" | "_" => VK_OEM_MINUS.0 as u32,
-            "=" | "+" => VK_OEM_PLUS.0 as u32,
-            "[" | "{" => VK_OEM_4.0 as u32,
-            "]" | "}" => VK_OEM_6.0 as u32,
-            "\\" | "|" => VK_OEM_5.0 as u32,
-            ";" | ":" => VK_OEM_1.0 as u32,
-            "'" | "\"" => VK_OEM_7.0 as u32,
-            "," | "<" => VK_OEM_COMMA.0 as u32,
-            "." | ">" => VK_OEM_PERIOD.0 as u32,
-            "/" | "?" => VK_OEM_2.0 as u32,
-
-            _ => return None,
+    /// 解析主键，无法识别时返回 [`HotkeyError::InvalidKey`]
+    pub fn parse_key(&self) -> Result<Key, HotkeyError> {
+        self.get_key().ok_or_else(|| HotkeyError::InvalidKey {
+            key: self.key.clone(),
         })
     }
 
+    /// 将按键名称转换为虚拟键码
+    pub fn get_vk_code(&self) -> Option<u32> {
+        self.get_key().map(|key| key.vk_code())
+    }
+
     /// 获取修饰键标志
     pub fn get_modifiers(&self) -> u32 {
-        use windows::Win32::UI::Input::KeyboardAndMouse::*;
-
         let mut mods = MOD_NOREPEAT; // 防止按住时重复触发
 
         if self.ctrl {
@@ -156,7 +290,7 @@ impl HotkeyConfig {
             mods |= MOD_WIN;
         }
 
-        mods.0
+        mods
     }
 }
 
@@ -169,6 +303,9 @@ pub struct Settings {
     /// 是否播放提示音
     #[serde(default = "default_true")]
     pub play_sound: bool,
+    /// 最多同时置顶的窗口数，不写则不限制
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_pinned: Option<usize>,
 }
 
 impl Default for Settings {
@@ -176,6 +313,7 @@ impl Default for Settings {
         Self {
             show_notification: true,
             play_sound: true,
+            max_pinned: None,
         }
     }
 }
@@ -184,81 +322,300 @@ fn default_true() -> bool {
     true
 }
 
-/// 应用配置
+/// 快捷键绑定：按下 `hotkey` 时执行 `action`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Binding {
+    /// 快捷键
+    pub hotkey: HotkeyConfig,
+    /// 动作
+    pub action: Action,
+}
+
+/// 组合键：按下 `leader` 后在 `timeout` 毫秒内按 `keys` 中的键，执行对应的动作
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChordConfig {
+    /// 引导键
+    pub leader: HotkeyConfig,
+    /// 等待第二个键的时间（毫秒）
+    #[serde(default = "default_chord_timeout")]
+    pub timeout: u64,
+    /// 第二个键（可以带修饰键，如 "T"、"Ctrl+T"）→ 动作，保持书写顺序
+    #[serde(with = "ordered_keys")]
+    pub keys: Vec<(String, Action)>,
+}
+
+/// 按书写顺序读写 `keys` 表
+mod ordered_keys {
+    use super::Action;
+    use serde::de::{MapAccess, Visitor};
+    use serde::{Deserializer, Serializer};
+    use std::fmt;
+
+    pub fn serialize<S: Serializer>(
+        keys: &[(String, Action)],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(keys.iter().map(|(key, action)| (key, action)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<(String, Action)>, D::Error> {
+        struct KeysVisitor;
+
+        impl<'de> Visitor<'de> for KeysVisitor {
+            type Value = Vec<(String, Action)>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("按键到动作的表，如 { T = \"toggle\" }")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut keys = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    keys.push(entry);
+                }
+                Ok(keys)
+            }
+        }
+
+        deserializer.deserialize_map(KeysVisitor)
+    }
+}
+
+/// 默认等待第二个键 1.5 秒
+pub const DEFAULT_CHORD_TIMEOUT: u64 = 1500;
+
+fn default_chord_timeout() -> u64 {
+    DEFAULT_CHORD_TIMEOUT
+}
+
+impl ChordConfig {
+    /// 解析第二个键，按 `keys` 的顺序
+    pub fn keys(&self) -> Result<Vec<(Accelerator, Action)>, HotkeyError> {
+        self.keys
+            .iter()
+            .map(|(key, action)| Ok((key.parse()?, *action)))
+            .collect()
+    }
+}
+
+/// 应用配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Config {
-    /// 快捷键配置
+    /// 快捷键配置（切换置顶）
     #[serde(default)]
     pub hotkey: HotkeyConfig,
     /// 设置
     #[serde(default)]
     pub settings: Settings,
+    /// 其他快捷键绑定
+    #[serde(default)]
+    pub bindings: Vec<Binding>,
+    /// 组合键
+    #[serde(default)]
+    pub chords: Vec<ChordConfig>,
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            hotkey: HotkeyConfig::default(),
-            settings: Settings::default(),
+impl Config {
+    /// 所有快捷键绑定：`[hotkey]` 切换置顶，然后是 `[[bindings]]`
+    pub fn all_bindings(&self) -> Vec<Binding> {
+        let toggle = Binding {
+            hotkey: self.hotkey.clone(),
+            action: Action::Toggle,
+        };
+        std::iter::once(toggle)
+            .chain(self.bindings.iter().cloned())
+            .collect()
+    }
+
+    /// 检查所有快捷键都能解析
+    pub fn validate(&self) -> Result<(), HotkeyError> {
+        for binding in self.all_bindings() {
+            binding.hotkey.candidates()?;
         }
+        for chord in &self.chords {
+            chord.leader.candidates()?;
+            chord.keys()?;
+        }
+        Ok(())
     }
-}
 
-impl Config {
-    /// 获取配置文件路径
+    /// 获取配置文件路径，见 [`location`]
     pub fn config_path() -> PathBuf {
-        let exe_path = std::env::current_exe().unwrap_or_default();
-        let exe_dir = exe_path.parent().unwrap_or(std::path::Path::new("."));
-        exe_dir.join("config.toml")
+        location::current().path.clone()
     }
 
-    /// 加载配置文件
-    pub fn load() -> Result<Self> {
-        let path = Self::config_path();
+    /// 加载配置：默认值、全局配置和用户配置合并后的结果，见 [`layers`]
+    pub fn load() -> Result<Self, ConfigError> {
+        Ok(Self::load_layered()?.config)
+    }
 
-        if path.exists() {
-            let content = fs::read_to_string(&path)
-                .with_context(|| format!("无法读取配置文件: {:?}", path))?;
-            let config: Config = toml::from_str(&content)
-                .with_context(|| "配置文件格式错误")?;
-            Ok(config)
-        } else {
-            // 创建默认配置文件
-            let config = Config::default();
-            config.save()?;
-            Ok(config)
+    /// 加载配置并保留每个值的来源，管理员策略优先于所有配置层
+    pub fn load_layered() -> Result<Layered, ConfigError> {
+        let policy = match location::policy_file() {
+            Some(path) => Policy::load(&path)?,
+            None => Policy::default(),
+        };
+        layers::load(
+            &Self::config_path(),
+            location::machine_config().as_deref(),
+            policy,
+        )
+    }
+
+    /// 加载指定的配置文件（及其 `include`，不含全局配置和策略），不存在时创建默认配置
+    pub fn load_from(path: &Path) -> Result<Self, ConfigError> {
+        Ok(layers::load(path, None, Policy::default())?.config)
+    }
+
+    /// 读取一个配置文件并升级到当前版本
+    ///
+    /// `rewrite` 为 true 时把升级结果写回文件，原文件备份为 `config.toml.v1.bak` 这样的文件；
+    /// 否则只在内存中升级（全局配置、共享文件可能是只读的）。
+    pub(crate) fn read_file(path: &Path, rewrite: bool) -> Result<String, ConfigError> {
+        let content = fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let Some(upgrade) = migrate::upgrade(&content, path)? else {
+            return Ok(content);
+        };
+        if rewrite {
+            let mut backup = path.as_os_str().to_owned();
+            backup.push(format!(".v{}.bak", upgrade.from));
+            let write = |target: &Path, content: &str| {
+                fs::write(target, content).map_err(|source| ConfigError::Write {
+                    path: target.to_path_buf(),
+                    source,
+                })
+            };
+            write(Path::new(&backup), &content)?;
+            write(path, &upgrade.content)?;
+        }
+        Ok(upgrade.content)
+    }
+
+    /// 解析并检查配置内容，`path` 只用于错误信息
+    ///
+    /// 返回第一个错误；警告不影响加载，可用 [`diagnostics::check`] 查看。
+    pub fn parse(content: &str, path: &Path) -> Result<Self, ConfigError> {
+        let report = diagnostics::check(content, path);
+        if let Some(error) = report.first_error() {
+            return Err(ConfigError::Parse {
+                path: path.to_path_buf(),
+                line: error.line,
+                column: error.column,
+                message: error.detail(),
+            });
         }
+        Ok(report.config.expect("没有错误时一定有配置"))
+    }
+
+    /// 只做反序列化，不做其他检查
+    pub(crate) fn deserialize_str(content: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(content)
     }
 
     /// 保存配置文件
-    pub fn save(&self) -> Result<()> {
+    ///
+    /// 文件已存在时只修改有变化的字段，保留注释、顺序和未知字段；
+    /// 不存在时按带注释的模板创建。
+    pub fn save(&self) -> Result<(), ConfigError> {
+        self.save_to(&Self::config_path())
+    }
+
+    /// 保存到指定文件
+    pub fn save_to(&self, path: &Path) -> Result<(), ConfigError> {
+        let content = match fs::read_to_string(path) {
+            Ok(original) => self.merge_into(&original, path)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                // 用户配置目录第一次使用时还不存在
+                if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+                    fs::create_dir_all(dir).map_err(|source| ConfigError::Write {
+                        path: path.to_path_buf(),
+                        source,
+                    })?;
+                }
+                self.to_toml_string()
+            }
+            Err(source) => {
+                return Err(ConfigError::Read {
+                    path: path.to_path_buf(),
+                    source,
+                })
+            }
+        };
+        fs::write(path, content).map_err(|source| ConfigError::Write {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// 把配置写入已有的配置内容，只修改有变化的字段
+    pub fn merge_into(&self, original: &str, path: &Path) -> Result<String, ConfigError> {
+        edit::merge(self, original).map_err(|e| {
+            let offset = e.span().map(|span| span.start).unwrap_or(0);
+            let (line, column) = line_column(original, offset);
+            ConfigError::Parse {
+                path: path.to_path_buf(),
+                line,
+                column,
+                message: e.message().trim_end().to_string(),
+            }
+        })
+    }
+
+    /// 用模板覆盖配置文件（丢弃原有内容，用于重置）
+    pub fn overwrite(&self) -> Result<(), ConfigError> {
         let path = Self::config_path();
-        let content = self.to_toml_string();
-        fs::write(&path, content)
-            .with_context(|| format!("无法写入配置文件: {:?}", path))?;
-        Ok(())
+        fs::write(&path, self.to_toml_string())
+            .map_err(|source| ConfigError::Write { path, source })
     }
 
     /// 生成带注释的 TOML 字符串
     fn to_toml_string(&self) -> String {
+        let mut content = self.to_toml_header();
+        for binding in &self.bindings {
+            content.push_str(&format!(
+                "\n[[bindings]]\nhotkey = {}\naction = \"{}\"\n",
+                binding.hotkey.to_toml(),
+                binding.action.name(),
+            ));
+        }
+        for chord in &self.chords {
+            let keys: toml::Table = chord
+                .keys
+                .iter()
+                .map(|(key, action)| (key.clone(), action.name().into()))
+                .collect();
+            content.push_str(&format!(
+                "\n[[chords]]\nleader = {}\ntimeout = {}\nkeys = {}\n",
+                chord.leader.to_toml(),
+                chord.timeout,
+                toml::Value::Table(keys),
+            ));
+        }
+        content
+    }
+
+    /// 版本、快捷键和 `[settings]` 部分
+    fn to_toml_header(&self) -> String {
         format!(
             r#"# =====================================================
 # 窗口置顶工具配置文件
 # Window TopMost Tool Configuration
 # =====================================================
 
-# 快捷键配置
-# Hotkey Configuration
-[hotkey]
-# 修饰键 (true/false)
-ctrl = {}
-alt = {}
-shift = {}
-win = {}
+# 配置文件版本，由程序维护，请勿修改
+version = {}
 
-# 主键 (支持: A-Z, 0-9, F1-F12, Space, Enter, Tab, Escape, 方向键等)
-# Examples: "Space", "T", "F9", "Enter"
-key = "{}"
+# 快捷键配置：修饰键 Ctrl、Alt、Shift、Win 加一个主键，用 + 连接
+# 主键支持（不区分大小写）:
+{}# Hotkey Configuration
+# Examples: "Ctrl+Space", "Ctrl+Alt+T", "Win+F9"
+# 写成数组时依次尝试，前面的被其他程序占用时使用后面的: ["Ctrl+Space", "Ctrl+Alt+Space"]
+hotkey = {}
 
 # 设置
 # Settings
@@ -268,14 +625,266 @@ show_notification = {}
 
 # 是否播放提示音 (true/false)
 play_sound = {}
+
+# 最多同时置顶的窗口数（不写则不限制）
+{}max_pinned = {}
+
+# 更多快捷键绑定（可选）
+# Additional Bindings
+# action: toggle, pin, unpin, unpin_all, open_config, reload, exit
+# [[bindings]]
+# hotkey = "Ctrl+Shift+Space"
+# action = "unpin_all"
+
+# 组合键（可选）：先按 leader，再在 timeout 毫秒内按第二个键
+# Chords
+# [[chords]]
+# leader = "Ctrl+K"
+# timeout = 1500
+# keys = {{ T = "toggle", U = "unpin_all" }}
 "#,
-            self.hotkey.ctrl,
-            self.hotkey.alt,
-            self.hotkey.shift,
-            self.hotkey.win,
-            self.hotkey.key,
+            CURRENT_VERSION,
+            keys::comment_list(),
+            self.hotkey.to_toml(),
             self.settings.show_notification,
             self.settings.play_sound,
+            if self.settings.max_pinned.is_some() {
+                ""
+            } else {
+                "# "
+            },
+            self.settings.max_pinned.unwrap_or(5),
         )
     }
 }
+
+/// 字节偏移 → 行号和列号（从 1 开始，列按字符计）
+pub(crate) fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    (line, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_error_reports_location() {
+        let content = "[hotkey]\nctrl = true\nkey = Space\n";
+        let err = Config::parse(content, Path::new("config.toml")).unwrap_err();
+        match err {
+            ConfigError::Parse { line, column, .. } => assert_eq!((line, column), (3, 7)),
+            other => panic!("unexpected error: {other}"),
+        }
+    }
+
+    #[test]
+    fn invalid_key_is_reported() {
+        let hotkey = HotkeyConfig {
+            key: "Spcae".into(),
+            ..HotkeyConfig::default()
+        };
+        assert!(matches!(
+            hotkey.parse_key(),
+            Err(HotkeyError::InvalidKey { key }) if key == "Spcae"
+        ));
+        assert_eq!(HotkeyConfig::default().parse_key().unwrap(), Key::Space);
+    }
+
+    #[test]
+    fn hotkey_accepts_accelerator_string() {
+        let config = Config::parse("hotkey = \"^!t\"", Path::new("config.toml")).unwrap();
+        assert!(config.hotkey.ctrl && config.hotkey.alt && !config.hotkey.shift);
+        assert_eq!(config.hotkey.key, "T");
+        assert_eq!(config.hotkey.display(), "Ctrl+Alt+T");
+
+        let fields = "[hotkey]\nwin = true\nalt = true\nkey = \"space\"\n";
+        let config = Config::parse(fields, Path::new("config.toml")).unwrap();
+        assert_eq!(config.hotkey.display(), "Alt+Win+Space");
+        let again: Accelerator = config.hotkey.display().parse().unwrap();
+        assert_eq!(again, config.hotkey.accelerator().unwrap());
+    }
+
+    #[test]
+    fn bad_accelerator_string_is_a_parse_error() {
+        let err = Config::parse("hotkey = \"Ctrl+Spcae\"", Path::new("config.toml")).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("第 6 个字符处 \"Spcae\""), "{}", message);
+    }
+
+    #[test]
+    fn hotkey_table_errors_stay_precise() {
+        let content = "[hotkey]\nctrl = \"yes\"\nkey = \"T\"\n";
+        let err = Config::parse(content, Path::new("config.toml")).unwrap_err();
+        match err {
+            ConfigError::Parse { line, message, .. } => {
+                assert_eq!(line, 2);
+                assert!(message.contains("\"left\""), "{}", message);
+            }
+            other => panic!("unexpected error: {other}"),
+        }
+    }
+
+    #[test]
+    fn hotkey_table_accepts_sides() {
+        let content = "[hotkey]\nctrl = \"left\"\nalt = true\nkey = \"sc:0x35\"\n";
+        let config = Config::parse(content, Path::new("config.toml")).unwrap();
+        assert!(config.hotkey.ctrl && config.hotkey.alt);
+        assert_eq!(config.hotkey.sides.ctrl, Side::Left);
+        assert_eq!(config.hotkey.canonical(), "LCtrl+Alt+sc:0x35");
+
+        // 序列化后再读回不丢失左右
+        let value = toml::Value::try_from(&config.hotkey).unwrap();
+        assert_eq!(value.get("ctrl").and_then(|v| v.as_str()), Some("left"));
+        let again = HotkeyConfig::deserialize(value).unwrap();
+        assert_eq!(
+            again.accelerator().unwrap(),
+            config.hotkey.accelerator().unwrap()
+        );
+    }
+
+    #[test]
+    fn hotkey_array_lists_fallbacks() {
+        let content = "hotkey = [\"Ctrl+Space\", \"^!Space\", \"F9\"]\n";
+        let config = Config::parse(content, Path::new("config.toml")).unwrap();
+        assert_eq!(config.hotkey.display(), "Ctrl+Space");
+        assert_eq!(
+            config.hotkey.candidate_names(),
+            ["Ctrl+Space", "Ctrl+Alt+Space", "F9"]
+        );
+        let again = HotkeyConfig::deserialize(config.hotkey.to_toml()).unwrap();
+        assert_eq!(again.fallbacks, config.hotkey.fallbacks);
+
+        for bad in ["hotkey = []", "hotkey = [\"Ctrl+Spcae\"]", "hotkey = [1]"] {
+            let err = Config::parse(bad, Path::new("config.toml")).unwrap_err();
+            assert!(matches!(err, ConfigError::Parse { .. }), "{bad}: {err}");
+        }
+    }
+
+    #[test]
+    fn chords_map_second_keys_to_actions() {
+        let content = "[[chords]]\nleader = \"Ctrl+K\"\nkeys = { T = \"toggle\", \"Shift+U\" = \"unpin_all\" }\n";
+        let config = Config::parse(content, Path::new("config.toml")).unwrap();
+        let chord = &config.chords[0];
+        assert_eq!(chord.leader.display(), "Ctrl+K");
+        assert_eq!(chord.timeout, DEFAULT_CHORD_TIMEOUT);
+        let keys: Vec<(String, Action)> = chord
+            .keys()
+            .unwrap()
+            .into_iter()
+            .map(|(key, action)| (key.to_string(), action))
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                ("T".to_string(), Action::Toggle),
+                ("Shift+U".to_string(), Action::UnpinAll)
+            ]
+        );
+
+        let mut config = config;
+        config.chords[0]
+            .keys
+            .push(("Ctrl+Spcae".into(), Action::Pin));
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn bindings_follow_the_toggle_hotkey() {
+        let content = r#"
+hotkey = "Ctrl+Space"
+
+[[bindings]]
+hotkey = "Ctrl+Shift+Space"
+action = "unpin_all"
+
+[[bindings]]
+hotkey = { alt = true, key = "F9" }
+action = "open_config"
+"#;
+        let config = Config::parse(content, Path::new("config.toml")).unwrap();
+        let bindings: Vec<_> = config
+            .all_bindings()
+            .iter()
+            .map(|b| (b.hotkey.display(), b.action))
+            .collect();
+        assert_eq!(
+            bindings,
+            vec![
+                ("Ctrl+Space".to_string(), Action::Toggle),
+                ("Ctrl+Shift+Space".to_string(), Action::UnpinAll),
+                ("Alt+F9".to_string(), Action::OpenConfig),
+            ]
+        );
+
+        // 保存后再读取结果不变
+        let saved = Config::parse(&config.to_toml_string(), Path::new("config.toml")).unwrap();
+        assert_eq!(saved.bindings.len(), 2);
+        assert_eq!(saved.bindings[1].hotkey.display(), "Alt+F9");
+        assert_eq!(saved.bindings[1].action, Action::OpenConfig);
+    }
+
+    #[test]
+    fn unknown_binding_action_is_rejected() {
+        let content = "[[bindings]]\nhotkey = \"F9\"\naction = \"explode\"\n";
+        let err = Config::parse(content, Path::new("config.toml")).unwrap_err();
+        assert!(matches!(err, ConfigError::Parse { line: 3, .. }), "{err}");
+    }
+
+    #[test]
+    fn validate_checks_every_binding() {
+        let mut config = Config::default();
+        assert!(config.validate().is_ok());
+
+        config.bindings.push(Binding {
+            hotkey: HotkeyConfig {
+                key: "Spcae".into(),
+                ..HotkeyConfig::default()
+            },
+            action: Action::UnpinAll,
+        });
+        assert!(matches!(
+            config.validate(),
+            Err(HotkeyError::InvalidKey { key }) if key == "Spcae"
+        ));
+    }
+
+    #[test]
+    fn template_lists_supported_keys() {
+        let content = Config::default().to_toml_string();
+        for (title, _) in Key::groups() {
+            assert!(content.contains(&format!("#   {}: ", title)), "{title}");
+        }
+        assert!(content.contains("F1-F24"));
+        let config = Config::parse(&content, Path::new("config.toml")).unwrap();
+        assert_eq!(config.hotkey.display(), Config::default().hotkey.display());
+    }
+
+    #[test]
+    fn old_config_is_upgraded_with_backup() {
+        let dir = std::env::temp_dir().join(format!("window-topmost-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        let old = "[hotkey]\nctrl = true\nalt = true\nkey = \"T\"\n";
+        fs::write(&path, old).unwrap();
+
+        let config = Config::load_from(&path).unwrap();
+        assert_eq!(config.hotkey.display(), "Ctrl+Alt+T");
+        assert_eq!(
+            fs::read_to_string(dir.join("config.toml.v1.bak")).unwrap(),
+            old
+        );
+        let upgraded = fs::read_to_string(&path).unwrap();
+        assert!(upgraded.contains("version = 2\n\nhotkey = \"Ctrl+Alt+T\"\n"));
+
+        // 已经是当前版本时不再改写
+        fs::remove_file(dir.join("config.toml.v1.bak")).unwrap();
+        Config::load_from(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), upgraded);
+        assert!(!dir.join("config.toml.v1.bak").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,654 @@
+//! 配置检查
+//!
+//! 逐项检查配置文件，给出带行号、列号的错误和警告：
+//! 语法错误、未知的表和字段、无效的按键（附近似的正确写法）、被系统保留的快捷键等。
+
+use crate::accelerator::{Accelerator, Side, Sides, MODIFIER_NAMES};
+use crate::action::Action;
+use crate::config::Config;
+use crate::error::HotkeyError;
+use crate::keys::Key;
+use crate::migrate::CURRENT_VERSION;
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use toml_edit::{ImDocument, Item, TableLike, Value};
+
+/// 顶层字段
+pub(crate) const TOP_LEVEL: [&str; 6] = [
+    "version", "include", "hotkey", "settings", "bindings", "chords",
+];
+/// `[hotkey]` 字段
+const HOTKEY_FIELDS: [&str; 5] = ["ctrl", "alt", "shift", "win", "key"];
+/// `[settings]` 字段
+pub(crate) const SETTINGS_FIELDS: [&str; 3] = ["show_notification", "play_sound", "max_pinned"];
+/// `[[bindings]]` 字段
+const BINDING_FIELDS: [&str; 2] = ["hotkey", "action"];
+/// `[[chords]]` 字段
+const CHORD_FIELDS: [&str; 3] = ["leader", "timeout", "keys"];
+
+/// 被系统保留的快捷键（Windows），通常无法注册或会与系统功能冲突
+const RESERVED: [(&str, &str); 16] = [
+    ("Win+L", "锁定计算机"),
+    ("Win+D", "显示桌面"),
+    ("Win+E", "打开文件资源管理器"),
+    ("Win+R", "运行"),
+    ("Win+I", "打开设置"),
+    ("Win+A", "打开操作中心"),
+    ("Win+S", "搜索"),
+    ("Win+X", "快速链接菜单"),
+    ("Win+Tab", "任务视图"),
+    ("Win+Space", "切换输入法"),
+    ("Alt+Tab", "切换窗口"),
+    ("Alt+F4", "关闭窗口"),
+    ("Ctrl+Escape", "打开开始菜单"),
+    ("Ctrl+Shift+Escape", "任务管理器"),
+    ("Ctrl+Alt+Delete", "安全选项"),
+    ("F12", "保留给调试器"),
+];
+
+/// 严重程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// 配置无法使用
+    Error,
+    /// 配置可以使用，但可能不是想要的效果
+    Warning,
+}
+
+/// 一条检查结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub path: PathBuf,
+    /// 行号（从 1 开始）
+    pub line: usize,
+    /// 列号（从 1 开始，按字符计）
+    pub column: usize,
+    pub message: String,
+    /// 可能的正确写法
+    pub suggestion: Option<String>,
+}
+
+impl Diagnostic {
+    /// 说明文字（含建议）
+    pub fn detail(&self) -> String {
+        match &self.suggestion {
+            Some(s) => format!("{}（是否想写 \"{}\"？）", self.message, s),
+            None => self.message.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "错误",
+            Severity::Warning => "警告",
+        };
+        write!(
+            f,
+            "{}:{}:{}: {}: {}",
+            self.path.display(),
+            self.line,
+            self.column,
+            severity,
+            self.detail()
+        )
+    }
+}
+
+/// 检查结果
+#[derive(Debug)]
+pub struct Report {
+    /// 没有错误时解析出的配置
+    pub config: Option<Config>,
+    /// 按出现位置排列
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Report {
+    /// 第一个错误
+    pub fn first_error(&self) -> Option<&Diagnostic> {
+        self.diagnostics
+            .iter()
+            .find(|d| d.severity == Severity::Error)
+    }
+
+    /// 错误数
+    pub fn errors(&self) -> usize {
+        self.count(Severity::Error)
+    }
+
+    /// 警告数
+    pub fn warnings(&self) -> usize {
+        self.count(Severity::Warning)
+    }
+
+    fn count(&self, severity: Severity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == severity)
+            .count()
+    }
+}
+
+/// 检查配置内容，`path` 只用于结果中的文件名
+pub fn check(content: &str, path: &Path) -> Report {
+    let mut checker = Checker {
+        content,
+        path,
+        diagnostics: Vec::new(),
+    };
+
+    match ImDocument::parse(content) {
+        Ok(doc) => checker.document(doc.as_table()),
+        Err(e) => checker.push(
+            Severity::Error,
+            e.span(),
+            e.message().trim_end().to_string(),
+            None,
+        ),
+    }
+
+    // 类型错误、缺少字段等交给反序列化报告；已有错误时不再重复报告
+    let mut config = None;
+    if checker
+        .diagnostics
+        .iter()
+        .all(|d| d.severity != Severity::Error)
+    {
+        match Config::deserialize_str(content) {
+            Ok(c) => config = Some(c),
+            Err(e) => {
+                let message = e.message().to_string();
+                checker.push(Severity::Error, e.span(), message, None);
+            }
+        }
+    }
+
+    let mut diagnostics = checker.diagnostics;
+    diagnostics.sort_by_key(|d| (d.line, d.column));
+    Report {
+        config,
+        diagnostics,
+    }
+}
+
+/// 在候选项中找拼写最接近的（不区分大小写）
+pub fn suggest<'a>(input: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let input = input.to_lowercase();
+    let len = input.chars().count();
+    if len < 2 {
+        return None;
+    }
+    let limit = (len / 3).max(1);
+    candidates
+        .into_iter()
+        .map(|c| (edit_distance(&input, &c.to_lowercase()), c))
+        .filter(|&(d, _)| d > 0 && d <= limit)
+        .min_by_key(|&(d, _)| d)
+        .map(|(_, c)| c)
+}
+
+/// 编辑距离（相邻字符交换算一次）
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+struct Checker<'a> {
+    content: &'a str,
+    path: &'a Path,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker<'_> {
+    fn push(
+        &mut self,
+        severity: Severity,
+        span: Option<Range<usize>>,
+        message: String,
+        suggestion: Option<String>,
+    ) {
+        let offset = span.map(|s| s.start).unwrap_or(0);
+        let (line, column) = crate::config::line_column(self.content, offset);
+        self.diagnostics.push(Diagnostic {
+            severity,
+            path: self.path.to_path_buf(),
+            line,
+            column,
+            message,
+            suggestion,
+        });
+    }
+
+    /// 检查表中是否有未知字段
+    fn fields(&mut self, table: &dyn TableLike, known: &[&str], context: &str) {
+        for (name, _) in table.iter() {
+            if known.contains(&name) {
+                continue;
+            }
+            let span = table.get_key_value(name).and_then(|(key, _)| key.span());
+            self.push(
+                Severity::Warning,
+                span,
+                format!("{}未知的字段 \"{}\"，将被忽略", context, name),
+                suggest(name, known.iter().copied()).map(str::to_string),
+            );
+        }
+    }
+
+    fn document(&mut self, root: &toml_edit::Table) {
+        self.fields(root, &TOP_LEVEL, "");
+
+        if let Some((_, version)) = root.get_key_value("version") {
+            let valid = version
+                .as_integer()
+                .is_some_and(|v| (1..=CURRENT_VERSION).contains(&v));
+            if !valid {
+                self.push(
+                    Severity::Error,
+                    version.span(),
+                    format!("version 应为 1 到 {} 之间的整数", CURRENT_VERSION),
+                    None,
+                );
+            }
+        }
+
+        if let Some((_, include)) = root.get_key_value("include") {
+            let valid = include
+                .as_array()
+                .is_some_and(|files| files.iter().all(|f| f.is_str()));
+            if !valid {
+                self.push(
+                    Severity::Error,
+                    include.span(),
+                    "include 应为文件路径的数组，如 [\"team.toml\"]".into(),
+                    None,
+                );
+            }
+        }
+
+        // 普通快捷键的首选组合：绑定之间不能重复，组合键的引导键也不能与它们相同
+        let mut hotkeys = Vec::new();
+        if let Some(item) = root.get("hotkey") {
+            hotkeys.extend(self.hotkey(item, "[hotkey]"));
+        }
+        if let Some(table) = root.get("settings").and_then(Item::as_table_like) {
+            self.fields(table, &SETTINGS_FIELDS, "[settings] ");
+        }
+        if let Some(item) = root.get("bindings") {
+            self.bindings(item, &mut hotkeys);
+        }
+        if let Some(item) = root.get("chords") {
+            self.chords(item, &hotkeys);
+        }
+    }
+
+    /// 检查快捷键（字符串或表），返回解析结果
+    fn hotkey(&mut self, item: &Item, context: &str) -> Option<Accelerator> {
+        if let Some(value) = item.as_value().filter(|v| v.is_str()) {
+            return self.accelerator(value);
+        }
+        // 首选组合和备选组合，数组元素的类型错误由反序列化报告
+        if let Some(array) = item.as_array() {
+            let candidates: Vec<_> = array.iter().map(|v| self.accelerator(v)).collect();
+            return candidates.into_iter().next().flatten();
+        }
+        let table = item.as_table_like()?;
+        self.fields(table, &HOTKEY_FIELDS, &format!("{} ", context));
+
+        let (_, key) = table.get_key_value("key")?;
+        let name = key.as_str()?;
+        let Some(parsed) = Key::from_name(name) else {
+            self.push(
+                Severity::Error,
+                key.span(),
+                HotkeyError::InvalidKey { key: name.into() }.to_string(),
+                suggest_key(name),
+            );
+            return None;
+        };
+
+        // 取值错误由反序列化报告
+        let flag = |field: &str| match table.get(field) {
+            Some(item) if item.is_str() => {
+                let side = item.as_str().and_then(Side::from_name);
+                (side.is_some(), side.unwrap_or_default())
+            }
+            item => (item.and_then(Item::as_bool).unwrap_or(false), Side::Either),
+        };
+        let (ctrl, alt, shift) = (flag("ctrl"), flag("alt"), flag("shift"));
+        let accel = Accelerator {
+            ctrl: ctrl.0,
+            alt: alt.0,
+            shift: shift.0,
+            win: flag("win").0,
+            sides: Sides {
+                ctrl: ctrl.1,
+                alt: alt.1,
+                shift: shift.1,
+            },
+            key: parsed,
+        };
+        self.reserved(accel, item.span().or_else(|| key.span()));
+        Some(accel)
+    }
+
+    /// 检查快捷键字符串
+    fn accelerator(&mut self, value: &Value) -> Option<Accelerator> {
+        let text = value.as_str()?;
+        match text.parse::<Accelerator>() {
+            Ok(accel) => {
+                self.reserved(accel, value.span());
+                Some(accel)
+            }
+            Err(err) => {
+                let (span, suggestion) = match &err {
+                    HotkeyError::Syntax { column, token, .. } => {
+                        // 定位到字符串内出错的字符（跳过引号和前导空格）
+                        let lead = text.len() - text.trim_start().len();
+                        let inner = text
+                            .trim_start()
+                            .chars()
+                            .take(column - 1)
+                            .map(char::len_utf8)
+                            .sum::<usize>();
+                        let span = value.span().map(|s| {
+                            let start = s.start + 1 + lead + inner;
+                            start..start
+                        });
+                        (span, suggest_key(token))
+                    }
+                    _ => (value.span(), None),
+                };
+                self.push(Severity::Error, span, err.to_string(), suggestion);
+                None
+            }
+        }
+    }
+
+    /// 系统保留的快捷键给出警告
+    fn reserved(&mut self, accel: Accelerator, span: Option<Range<usize>>) {
+        let hit = RESERVED
+            .iter()
+            .find(|(s, _)| s.parse::<Accelerator>().is_ok_and(|r| r.overlaps(&accel)));
+        if let Some((_, purpose)) = hit {
+            self.push(
+                Severity::Warning,
+                span,
+                format!("快捷键 {} 被系统保留（{}），可能无法注册", accel, purpose),
+                None,
+            );
+        }
+    }
+
+    /// 检查动作名称
+    fn action(&mut self, action: &Item) {
+        if let Some(name) = action.as_str().filter(|n| Action::from_name(n).is_none()) {
+            self.push(
+                Severity::Error,
+                action.span(),
+                format!("未知的动作 \"{}\"", name),
+                suggest(name, Action::ALL.iter().map(|a| a.name())).map(str::to_string),
+            );
+        }
+    }
+
+    /// 检查 `[[bindings]]`；`seen` 为已经检查过的快捷键（`[hotkey]`），各个绑定的快捷键追加在后面
+    fn bindings(&mut self, item: &Item, seen: &mut Vec<Accelerator>) {
+        for table in tables(item) {
+            self.fields(table, &BINDING_FIELDS, "[[bindings]] ");
+
+            if let Some(action) = table.get("action") {
+                self.action(action);
+            }
+
+            let Some((_, hotkey)) = table.get_key_value("hotkey") else {
+                continue;
+            };
+            if let Some(accel) = self.hotkey(hotkey, "[[bindings]] hotkey") {
+                if seen.iter().any(|s| s.overlaps(&accel)) {
+                    self.push(
+                        Severity::Warning,
+                        hotkey.span(),
+                        format!("快捷键 {} 重复绑定，只有第一个有效", accel),
+                        None,
+                    );
+                }
+                seen.push(accel);
+            }
+        }
+    }
+
+    /// 检查 `[[chords]]`；`hotkeys` 为普通快捷键，引导键与它们相同时无法注册
+    fn chords(&mut self, item: &Item, hotkeys: &[Accelerator]) {
+        let mut leaders: Vec<Accelerator> = Vec::new();
+        for table in tables(item) {
+            self.fields(table, &CHORD_FIELDS, "[[chords]] ");
+            if let Some(leader) = table.get("leader") {
+                if let Some(accel) = self.hotkey(leader, "[[chords]] leader") {
+                    if hotkeys.iter().chain(&leaders).any(|h| h.overlaps(&accel)) {
+                        self.push(
+                            Severity::Warning,
+                            leader.span(),
+                            format!("引导键 {} 已经用作其他快捷键，这个组合键无效", accel),
+                            None,
+                        );
+                    }
+                    leaders.push(accel);
+                }
+            }
+            let Some(keys) = table.get("keys").and_then(Item::as_table_like) else {
+                continue;
+            };
+            let mut seen: Vec<Accelerator> = Vec::new();
+            for (name, action) in keys.iter() {
+                let span = keys.get_key_value(name).and_then(|(key, _)| key.span());
+                match name.parse::<Accelerator>() {
+                    Ok(accel) if seen.iter().any(|s| s.overlaps(&accel)) => self.push(
+                        Severity::Warning,
+                        span,
+                        format!("第二个键 {} 与前面的键相同，只有第一个有效", name),
+                        None,
+                    ),
+                    Ok(accel) => seen.push(accel),
+                    Err(err) => {
+                        let suggestion = match &err {
+                            HotkeyError::Syntax { token, .. } => suggest_key(token),
+                            _ => None,
+                        };
+                        self.push(Severity::Error, span, err.to_string(), suggestion);
+                    }
+                }
+                self.action(action);
+            }
+        }
+    }
+}
+
+/// 表数组（`[[x]]` 或内联表的数组）中的各个表；类型错误由反序列化报告
+fn tables(item: &Item) -> Vec<&dyn TableLike> {
+    match item {
+        Item::ArrayOfTables(array) => array.iter().map(|t| t as &dyn TableLike).collect(),
+        Item::Value(Value::Array(array)) => array
+            .iter()
+            .filter_map(|v| v.as_inline_table())
+            .map(|t| t as &dyn TableLike)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// 按键名和修饰键名中最接近的写法
+fn suggest_key(token: &str) -> Option<String> {
+    let names: Vec<String> = Key::all().iter().map(Key::name).collect();
+    let candidates = names
+        .iter()
+        .map(String::as_str)
+        .chain(MODIFIER_NAMES.iter().copied());
+    suggest(token, candidates).map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostics(content: &str) -> Vec<String> {
+        check(content, Path::new("config.toml"))
+            .diagnostics
+            .iter()
+            .map(|d| d.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn suggests_close_matches() {
+        assert_eq!(suggest_key("Spcae").as_deref(), Some("Space"));
+        assert_eq!(suggest_key("Crtl").as_deref(), Some("Ctrl"));
+        assert_eq!(suggest_key("escpae").as_deref(), Some("Escape"));
+        assert_eq!(suggest_key("Banana"), None);
+        assert_eq!(suggest("setings", TOP_LEVEL), Some("settings"));
+    }
+
+    #[test]
+    fn reports_bad_key_with_location_and_suggestion() {
+        let content = "[hotkey]\nctrl = true\nkey = \"Spcae\"\n";
+        assert_eq!(
+            diagnostics(content),
+            vec!["config.toml:3:7: 错误: 无效的快捷键: Spcae（是否想写 \"Space\"？）"]
+        );
+
+        let content = "hotkey = \"Ctrl+Spcae\"\n";
+        assert_eq!(
+            diagnostics(content),
+            vec![
+                "config.toml:1:16: 错误: 无效的快捷键 \"Ctrl+Spcae\"，第 6 个字符处 \"Spcae\" 不是有效的按键（是否想写 \"Space\"？）"
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_chord_keys_and_actions() {
+        let content =
+            "[[chords]]\nleader = \"Ctrl+K\"\nkeys = { Spcae = \"toggle\", U = \"unpin-all\" }\n";
+        assert_eq!(
+            diagnostics(content),
+            vec![
+                "config.toml:3:10: 错误: 无效的快捷键 \"Spcae\"，第 1 个字符处 \"Spcae\" 不是有效的按键（是否想写 \"Space\"？）",
+                "config.toml:3:32: 错误: 未知的动作 \"unpin-all\"（是否想写 \"unpin_all\"？）",
+            ]
+        );
+    }
+
+    #[test]
+    fn warns_about_overlapping_chord_keys() {
+        let content = "hotkey = \"Ctrl+K\"\n\n[[chords]]\nleader = \"Ctrl+K\"\nkeys = { T = \"toggle\", t = \"pin\" }\n";
+        assert_eq!(
+            diagnostics(content),
+            vec![
+                "config.toml:4:10: 警告: 引导键 Ctrl+K 已经用作其他快捷键，这个组合键无效",
+                "config.toml:5:24: 警告: 第二个键 t 与前面的键相同，只有第一个有效",
+            ]
+        );
+    }
+
+    #[test]
+    fn flags_unknown_tables_and_fields() {
+        let content = "[setings]\nplay_sound = false\n\n[hotkey]\nctlr = true\nkey = \"T\"\n";
+        let report = check(content, Path::new("config.toml"));
+        assert_eq!(report.errors(), 0);
+        assert_eq!(
+            report
+                .diagnostics
+                .iter()
+                .map(|d| d.detail())
+                .collect::<Vec<_>>(),
+            vec![
+                "未知的字段 \"setings\"，将被忽略（是否想写 \"settings\"？）",
+                "[hotkey] 未知的字段 \"ctlr\"，将被忽略（是否想写 \"ctrl\"？）",
+            ]
+        );
+        assert_eq!(
+            (report.diagnostics[1].line, report.diagnostics[1].column),
+            (5, 1)
+        );
+        assert!(report.config.is_some());
+    }
+
+    #[test]
+    fn version_must_be_supported() {
+        assert!(diagnostics("version = 2\nhotkey = \"F9\"\n").is_empty());
+        assert_eq!(
+            diagnostics("version = \"2\"\n"),
+            vec!["config.toml:1:11: 错误: version 应为 1 到 2 之间的整数"]
+        );
+    }
+
+    #[test]
+    fn warns_about_reserved_and_duplicate_bindings() {
+        let content = r#"
+hotkey = "Win+L"
+
+[[bindings]]
+hotkey = "Ctrl+Shift+Space"
+action = "unpin_all"
+
+[[bindings]]
+hotkey = "ctrl+shift+space"
+action = "unpinall"
+"#;
+        let report = check(content, Path::new("config.toml"));
+        let details: Vec<_> = report.diagnostics.iter().map(|d| d.detail()).collect();
+        assert_eq!(
+            details,
+            vec![
+                "快捷键 Win+L 被系统保留（锁定计算机），可能无法注册",
+                "快捷键 Ctrl+Shift+Space 重复绑定，只有第一个有效",
+                "未知的动作 \"unpinall\"（是否想写 \"unpin_all\"？）",
+            ]
+        );
+        assert_eq!(report.errors(), 1);
+        assert!(report.config.is_none());
+    }
+
+    #[test]
+    fn binding_repeating_the_toggle_hotkey_is_a_duplicate() {
+        let content = "hotkey = \"F9\"\n\n[[bindings]]\nhotkey = \"F9\"\naction = \"exit\"\n";
+        assert_eq!(
+            diagnostics(content),
+            vec!["config.toml:4:10: 警告: 快捷键 F9 重复绑定，只有第一个有效"]
+        );
+    }
+
+    #[test]
+    fn syntax_and_type_errors_keep_their_location() {
+        let report = check("[hotkey]\nkey = Space\n", Path::new("config.toml"));
+        let error = report.first_error().unwrap();
+        assert_eq!((error.line, error.column), (2, 7));
+
+        let report = check("[settings]\nplay_sound = 1\n", Path::new("config.toml"));
+        let error = report.first_error().unwrap();
+        assert_eq!(error.line, 2);
+    }
+}
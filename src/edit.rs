@@ -0,0 +1,396 @@
+//! 保留格式的配置写入
+//!
+//! 在用户原有的配置文件上只修改有变化的字段，注释、顺序和未知字段都原样保留。
+
+use crate::accelerator::{Accelerator, Side};
+use crate::config::{Binding, ChordConfig, Config, HotkeyConfig, Settings, DEFAULT_CHORD_TIMEOUT};
+use crate::keys::Key;
+use toml_edit::{
+    ArrayOfTables, DocumentMut, InlineTable, Item, Table, TableLike, TomlError, Value,
+};
+
+/// 把 `config` 写入已有的配置内容，返回新的内容
+pub fn merge(config: &Config, original: &str) -> Result<String, TomlError> {
+    let mut doc: DocumentMut = original.parse()?;
+    let existing: Vec<String> = doc.iter().map(|(key, _)| key.to_string()).collect();
+
+    merge_hotkey(doc.as_table_mut(), "hotkey", &config.hotkey, true);
+    merge_settings(doc.as_table_mut(), &config.settings);
+    merge_tables(
+        doc.as_table_mut(),
+        "bindings",
+        &config.bindings,
+        merge_binding,
+    );
+    merge_tables(doc.as_table_mut(), "chords", &config.chords, merge_chord);
+
+    // 新增的表写在文件末尾，原来末尾的注释保持在它们前面
+    let trailing = doc.trailing().as_str().unwrap_or_default().to_string();
+    if !trailing.is_empty() {
+        let added = doc
+            .as_table_mut()
+            .iter_mut()
+            .filter(|(key, _)| !existing.iter().any(|k| k == key.get()))
+            .find_map(|(_, item)| match item {
+                Item::Table(table) => Some(table),
+                Item::ArrayOfTables(array) => array.get_mut(0),
+                _ => None,
+            });
+        if let Some(table) = added {
+            table.decor_mut().set_prefix(format!("{}\n", trailing));
+            doc.set_trailing("");
+        }
+    }
+
+    Ok(doc.to_string())
+}
+
+/// 替换字段的值，保留原值前后的空格和注释
+pub(crate) fn replace(table: &mut dyn TableLike, key: &str, value: Value) {
+    match table.get_mut(key).and_then(Item::as_value_mut) {
+        Some(old) => {
+            let decor = old.decor().clone();
+            *old = value;
+            *old.decor_mut() = decor;
+        }
+        None => {
+            table.insert(key, Item::Value(value));
+        }
+    }
+}
+
+/// 布尔字段，缺省值为 `default`
+fn set_bool(table: &mut dyn TableLike, key: &str, value: bool, default: bool) {
+    let current = table.get(key).and_then(Item::as_bool).unwrap_or(default);
+    if current != value {
+        replace(table, key, value.into());
+    }
+}
+
+/// 字符串字段
+fn set_str(table: &mut dyn TableLike, key: &str, value: &str) {
+    if table.get(key).and_then(Item::as_str) != Some(value) {
+        replace(table, key, value.into());
+    }
+}
+
+/// 可以限定左右的修饰键：`true`/`false` 或 `"left"`/`"right"`
+fn set_flag(table: &mut dyn TableLike, key: &str, on: bool, side: Side) {
+    match side.name().filter(|_| on) {
+        Some(name) => {
+            let current = table
+                .get(key)
+                .and_then(Item::as_str)
+                .and_then(Side::from_name);
+            if current != Some(side) {
+                replace(table, key, name.into());
+            }
+        }
+        None if table.get(key).is_some_and(Item::is_str) => replace(table, key, on.into()),
+        None => set_bool(table, key, on, false),
+    }
+}
+
+/// 快捷键写入配置文件的值：字符串，有备选组合时为字符串数组
+pub(crate) fn hotkey_value(hotkey: &HotkeyConfig) -> Value {
+    if hotkey.fallbacks.is_empty() {
+        hotkey.canonical().into()
+    } else {
+        Value::Array(hotkey.candidate_names().into_iter().collect())
+    }
+}
+
+/// 快捷键：保持原来的写法（字符串或表），`omit_default` 时缺省值不写入
+fn merge_hotkey(parent: &mut dyn TableLike, key: &str, hotkey: &HotkeyConfig, omit_default: bool) {
+    let wanted = hotkey.accelerator().ok();
+
+    let Some(item) = parent.get_mut(key) else {
+        let default = wanted == HotkeyConfig::default().accelerator().ok();
+        if !(omit_default && default && hotkey.fallbacks.is_empty()) {
+            parent.insert(key, Item::Value(hotkey_value(hotkey)));
+        }
+        return;
+    };
+
+    // 备选组合只能写成数组
+    if let Some(array) = item.as_array() {
+        let current: Option<Vec<Accelerator>> =
+            array.iter().map(|v| v.as_str()?.parse().ok()).collect();
+        if current.is_none() || current != hotkey.candidates().ok() {
+            replace(parent, key, hotkey_value(hotkey));
+        }
+        return;
+    }
+    if !hotkey.fallbacks.is_empty() {
+        replace(parent, key, hotkey_value(hotkey));
+        return;
+    }
+
+    if let Some(text) = item.as_str() {
+        if text.parse::<Accelerator>().ok() != wanted || wanted.is_none() {
+            replace(parent, key, hotkey.canonical().into());
+        }
+        return;
+    }
+
+    let Some(table) = item.as_table_like_mut() else {
+        replace(parent, key, hotkey.canonical().into());
+        return;
+    };
+    set_flag(table, "ctrl", hotkey.ctrl, hotkey.sides.ctrl);
+    set_flag(table, "alt", hotkey.alt, hotkey.sides.alt);
+    set_flag(table, "shift", hotkey.shift, hotkey.sides.shift);
+    set_bool(table, "win", hotkey.win, false);
+    // 同一个键的不同写法（如 "space" 和 "Space"）不算修改
+    let current = table
+        .get("key")
+        .and_then(Item::as_str)
+        .and_then(Key::from_name);
+    let same_key = current.is_some() && current == hotkey.get_key();
+    if !same_key {
+        set_str(table, "key", &hotkey.key);
+    }
+}
+
+fn merge_settings(root: &mut Table, settings: &Settings) {
+    let defaults = Settings::default();
+    if root.get("settings").is_none() {
+        if settings.show_notification == defaults.show_notification
+            && settings.play_sound == defaults.play_sound
+            && settings.max_pinned == defaults.max_pinned
+        {
+            return;
+        }
+        root.insert("settings", Item::Table(Table::new()));
+    }
+
+    let Some(table) = root.get_mut("settings").and_then(Item::as_table_like_mut) else {
+        return;
+    };
+    set_bool(
+        table,
+        "show_notification",
+        settings.show_notification,
+        defaults.show_notification,
+    );
+    set_bool(
+        table,
+        "play_sound",
+        settings.play_sound,
+        defaults.play_sound,
+    );
+    let current = table.get("max_pinned").and_then(Item::as_integer);
+    match settings.max_pinned {
+        Some(max) if current != Some(max as i64) => {
+            replace(table, "max_pinned", (max as i64).into())
+        }
+        None if table.contains_key("max_pinned") => {
+            table.remove("max_pinned");
+        }
+        _ => {}
+    }
+}
+
+fn merge_binding(table: &mut dyn TableLike, binding: &Binding) {
+    merge_hotkey(table, "hotkey", &binding.hotkey, false);
+    set_str(table, "action", binding.action.name());
+}
+
+fn merge_chord(table: &mut dyn TableLike, chord: &ChordConfig) {
+    merge_hotkey(table, "leader", &chord.leader, false);
+    let timeout = i64::try_from(chord.timeout).unwrap_or(i64::MAX);
+    let current = table.get("timeout").and_then(Item::as_integer);
+    if current.unwrap_or(DEFAULT_CHORD_TIMEOUT as i64) != timeout {
+        replace(table, "timeout", timeout.into());
+    }
+
+    let Some(keys) = table.get_mut("keys").and_then(Item::as_table_like_mut) else {
+        let keys: InlineTable = chord
+            .keys
+            .iter()
+            .map(|(key, action)| (key.as_str(), Value::from(action.name())))
+            .collect();
+        table.insert("keys", Item::Value(Value::InlineTable(keys)));
+        return;
+    };
+    let removed: Vec<String> = keys
+        .iter()
+        .map(|(key, _)| key.to_string())
+        .filter(|key| chord.keys.iter().all(|(k, _)| k != key))
+        .collect();
+    for key in removed {
+        keys.remove(&key);
+    }
+    for (key, action) in &chord.keys {
+        set_str(keys, key, action.name());
+    }
+}
+
+/// 逐项修改已有的表数组（如 `[[bindings]]`），多出的追加、少了的删除
+fn merge_tables<T>(root: &mut Table, key: &str, items: &[T], merge: fn(&mut dyn TableLike, &T)) {
+    match root.get_mut(key) {
+        None if items.is_empty() => {}
+        Some(Item::ArrayOfTables(array)) => {
+            for (i, item) in items.iter().enumerate() {
+                match array.get_mut(i) {
+                    Some(table) => merge(table, item),
+                    None => {
+                        let mut table = Table::new();
+                        merge(&mut table, item);
+                        array.push(table);
+                    }
+                }
+            }
+            while array.len() > items.len() {
+                array.remove(array.len() - 1);
+            }
+        }
+        Some(Item::Value(Value::Array(array))) => {
+            for (i, item) in items.iter().enumerate() {
+                match array.get_mut(i).and_then(Value::as_inline_table_mut) {
+                    Some(table) => merge(table, item),
+                    None => {
+                        let mut table = InlineTable::new();
+                        merge(&mut table, item);
+                        array.push(table);
+                    }
+                }
+            }
+            while array.len() > items.len() {
+                array.remove(array.len() - 1);
+            }
+        }
+        _ => {
+            let mut array = ArrayOfTables::new();
+            for item in items {
+                let mut table = Table::new();
+                merge(&mut table, item);
+                array.push(table);
+            }
+            root.insert(key, Item::ArrayOfTables(array));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::Action;
+    use std::path::Path;
+
+    const ORIGINAL: &str = r#"# 我的配置
+hotkey = "^!t"   # AutoHotkey 写法
+
+[settings]
+# 安静一点
+play_sound = true  # 以后再关
+theme = "dark"
+
+[[bindings]]
+hotkey = { ctrl = true, shift = true, key = "space" }
+action = "unpin_all"
+"#;
+
+    fn load(content: &str) -> Config {
+        Config::parse(content, Path::new("config.toml")).unwrap()
+    }
+
+    #[test]
+    fn unchanged_config_is_written_back_verbatim() {
+        let config = load(ORIGINAL);
+        assert_eq!(merge(&config, ORIGINAL).unwrap(), ORIGINAL);
+    }
+
+    #[test]
+    fn only_modified_fields_change() {
+        let mut config = load(ORIGINAL);
+        config.settings.play_sound = false;
+        config.hotkey = "Ctrl+Alt+Y".parse::<Accelerator>().unwrap().into();
+
+        let merged = merge(&config, ORIGINAL).unwrap();
+        let expected = ORIGINAL
+            .replace("\"^!t\"", "\"Ctrl+Alt+Y\"")
+            .replace("play_sound = true", "play_sound = false");
+        assert_eq!(merged, expected);
+    }
+
+    #[test]
+    fn bindings_are_updated_in_place_and_appended() {
+        let mut config = load(ORIGINAL);
+        config.bindings[0].action = Action::Unpin;
+        config.bindings.push(Binding {
+            hotkey: "F9".parse::<Accelerator>().unwrap().into(),
+            action: Action::OpenConfig,
+        });
+
+        let merged = merge(&config, ORIGINAL).unwrap();
+        assert!(merged.contains(
+            "hotkey = { ctrl = true, shift = true, key = \"space\" }\naction = \"unpin\"\n"
+        ));
+        assert!(merged.ends_with("[[bindings]]\nhotkey = \"F9\"\naction = \"open_config\"\n"));
+        assert_eq!(load(&merged).bindings.len(), 2);
+
+        config.bindings.clear();
+        let merged = merge(&config, ORIGINAL).unwrap();
+        assert!(!merged.contains("[[bindings]]"));
+        assert!(merged.contains("theme = \"dark\""));
+    }
+
+    #[test]
+    fn chord_keys_are_updated_in_place() {
+        let original = "[[chords]]\nleader = \"Ctrl+K\"\n\n[chords.keys]\nT = \"toggle\" # 切换\nU = \"unpin_all\"\n";
+        let mut config = load(original);
+        assert_eq!(merge(&config, original).unwrap(), original);
+
+        let chord = &mut config.chords[0];
+        chord.timeout = 800;
+        chord.keys.retain(|(key, _)| key != "U");
+        chord.keys.push(("R".into(), Action::Reload));
+        assert_eq!(
+            merge(&config, original).unwrap(),
+            "[[chords]]\nleader = \"Ctrl+K\"\ntimeout = 800\n\n[chords.keys]\nT = \"toggle\" # 切换\nR = \"reload\"\n"
+        );
+    }
+
+    #[test]
+    fn table_hotkeys_keep_left_and_right() {
+        let original = "[hotkey]\nctrl = true\nkey = \"T\"\n";
+        let mut config = load(original);
+        config.hotkey = "RCtrl+T".parse::<Accelerator>().unwrap().into();
+        let merged = merge(&config, original).unwrap();
+        assert_eq!(merged, "[hotkey]\nctrl = \"right\"\nkey = \"T\"\n");
+
+        config.hotkey = "Ctrl+T".parse::<Accelerator>().unwrap().into();
+        assert_eq!(merge(&config, &merged).unwrap(), original);
+    }
+
+    #[test]
+    fn fallbacks_are_written_as_an_array() {
+        let original = "hotkey = \"Ctrl+Space\" # 切换\n";
+        let mut config = load(original);
+        config.hotkey.fallbacks = vec!["F9".parse().unwrap()];
+        let merged = merge(&config, original).unwrap();
+        assert_eq!(merged, "hotkey = [\"Ctrl+Space\", \"F9\"] # 切换\n");
+
+        // 同一组合的不同写法不算修改
+        let spelled = "hotkey = [\"^space\", \"f9\"]\n";
+        assert_eq!(merge(&load(spelled), spelled).unwrap(), spelled);
+
+        config.hotkey.fallbacks.clear();
+        assert_eq!(merge(&config, &merged).unwrap(), original);
+    }
+
+    #[test]
+    fn missing_sections_are_added_only_when_needed() {
+        let original = "# 空配置\n";
+        assert_eq!(merge(&Config::default(), original).unwrap(), original);
+
+        let mut config = Config::default();
+        config.settings.show_notification = false;
+        let merged = merge(&config, original).unwrap();
+        assert_eq!(
+            merged,
+            "# 空配置\n\n[settings]\nshow_notification = false\n"
+        );
+    }
+}
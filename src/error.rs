@@ -0,0 +1,225 @@
+//! 错误类型
+//!
+//! 每个子系统一个错误枚举，调用者可以按类型分别处理。
+
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// 配置文件错误
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    /// 读取失败
+    #[error("无法读取配置文件: {path:?}")]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    /// 格式错误，`line`、`column` 从 1 开始
+    #[error("配置文件格式错误 ({}:{line}:{column}): {message}", path.display())]
+    Parse {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// 配置文件版本比程序支持的新
+    #[error("配置文件版本 {found} 高于程序支持的版本 {supported} ({})", path.display())]
+    Version {
+        path: PathBuf,
+        found: i64,
+        supported: i64,
+    },
+    /// 违反管理员策略，`line`、`column` 从 1 开始
+    #[error("配置违反管理员策略 ({}:{line}:{column}): {message}", path.display())]
+    Policy {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// 配置项路径或值无效（命令行 `config get` / `config set`）
+    #[error("配置项 {key} 无效: {message}")]
+    Key { key: String, message: String },
+    /// `include` 形成了循环
+    #[error("配置文件循环包含: {}", path.display())]
+    IncludeCycle { path: PathBuf },
+    /// 写入失败
+    #[error("无法写入配置文件: {path:?}")]
+    Write {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+}
+
+impl ConfigError {
+    /// 出错的文件；`Key` 错误与文件无关
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            ConfigError::Read { path, .. }
+            | ConfigError::Parse { path, .. }
+            | ConfigError::Version { path, .. }
+            | ConfigError::Policy { path, .. }
+            | ConfigError::IncludeCycle { path }
+            | ConfigError::Write { path, .. } => Some(path),
+            ConfigError::Key { .. } => None,
+        }
+    }
+}
+
+/// 快捷键错误
+#[derive(Debug, Error)]
+pub enum HotkeyError {
+    /// 无法识别的按键名称
+    #[error("无效的快捷键: {key}")]
+    InvalidKey { key: String },
+    /// 快捷键字符串格式错误，`column` 为出错位置（从 1 开始，按字符计）
+    #[error("无效的快捷键 \"{input}\"，第 {column} 个字符处 \"{token}\" {message}")]
+    Syntax {
+        input: String,
+        column: usize,
+        token: String,
+        message: String,
+    },
+    /// 快捷键已被其他程序占用
+    #[error("快捷键 {hotkey} 已被其他程序占用")]
+    Conflict { hotkey: String },
+    /// 与本程序的另一个绑定相同，两个都按下时只能触发一个
+    #[error("快捷键 {hotkey} 已经绑定到动作 {action}")]
+    Duplicate { hotkey: String, action: String },
+    /// 其他注册失败
+    #[error("注册快捷键 {hotkey} 失败: {source}")]
+    Register {
+        hotkey: String,
+        #[source]
+        source: io::Error,
+    },
+}
+
+impl HotkeyError {
+    /// 被其他程序占用，对方释放后可以再注册
+    pub fn is_blocked(&self) -> bool {
+        matches!(self, HotkeyError::Conflict { .. })
+    }
+}
+
+/// 系统托盘错误
+#[derive(Debug, Error)]
+pub enum TrayError {
+    /// 创建托盘窗口失败
+    #[error("创建托盘窗口失败: {0}")]
+    CreateWindow(#[source] io::Error),
+    /// 加载图标失败
+    #[error("加载托盘图标失败: {0}")]
+    LoadIcon(#[source] io::Error),
+    /// 添加托盘图标失败
+    #[error("添加托盘图标失败")]
+    AddIcon,
+}
+
+/// 启动失败的原因
+#[derive(Debug, Error)]
+pub enum StartupError {
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error(transparent)]
+    Hotkey(#[from] HotkeyError),
+    #[error(transparent)]
+    Tray(#[from] TrayError),
+}
+
+impl StartupError {
+    /// 建议的解决办法
+    pub fn hint(&self) -> &'static str {
+        match self {
+            StartupError::Config(ConfigError::Read { .. }) => "请检查配置文件是否存在且可以读取。",
+            StartupError::Config(ConfigError::Parse { .. }) => {
+                "请按提示的行号修改配置文件，或将其重置为默认配置。"
+            }
+            StartupError::Config(ConfigError::Version { .. }) => {
+                "配置文件是由更新版本的程序写入的，请升级程序，或将其重置为默认配置。"
+            }
+            StartupError::Config(ConfigError::Policy { .. }) => {
+                "该设置已被管理员锁定，请从配置文件中删除或改回这一项。"
+            }
+            StartupError::Config(ConfigError::IncludeCycle { .. }) => {
+                "请检查各个配置文件的 include，去掉互相包含的文件。"
+            }
+            StartupError::Config(ConfigError::Key { .. }) => {
+                "请用 config show --effective 查看可用的配置项。"
+            }
+            StartupError::Config(ConfigError::Write { .. }) => {
+                "请检查程序所在目录是否可写，或把程序移到有写入权限的目录。"
+            }
+            StartupError::Hotkey(HotkeyError::InvalidKey { .. } | HotkeyError::Syntax { .. }) => {
+                "请在配置文件中把 key 改为支持的按键，例如 \"Space\"、\"T\"、\"F9\"。"
+            }
+            StartupError::Hotkey(HotkeyError::Conflict { .. }) => {
+                "请关闭占用该快捷键的程序，或在配置文件中换一个快捷键。"
+            }
+            StartupError::Hotkey(HotkeyError::Duplicate { .. }) => {
+                "请在配置文件中删除重复的绑定，或换一个快捷键。"
+            }
+            StartupError::Hotkey(HotkeyError::Register { .. }) => {
+                "请在配置文件中换一个快捷键后重试。"
+            }
+            StartupError::Tray(_) => "请稍后重新启动程序，或重启资源管理器后重试。",
+        }
+    }
+
+    /// 完整的报告文本：错误、底层原因和建议
+    pub fn report(&self) -> String {
+        let mut text = self.to_string();
+        let mut source = std::error::Error::source(self);
+        // transparent 变体的 source 是内层错误的 source
+        while let Some(cause) = source {
+            text.push_str(&format!("\n原因: {}", cause));
+            source = cause.source();
+        }
+        text.push_str(&format!("\n\n建议: {}", self.hint()));
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_includes_cause_and_hint() {
+        let err = StartupError::from(ConfigError::Read {
+            path: PathBuf::from("config.toml"),
+            source: io::Error::new(io::ErrorKind::PermissionDenied, "拒绝访问"),
+        });
+        let report = err.report();
+        assert!(report.starts_with("无法读取配置文件: \"config.toml\""));
+        assert!(report.contains("\n原因: 拒绝访问"));
+        assert!(report.ends_with(err.hint()));
+
+        let err = StartupError::from(HotkeyError::Conflict {
+            hotkey: "Ctrl + Space".into(),
+        });
+        assert_eq!(
+            err.report(),
+            format!(
+                "快捷键 Ctrl + Space 已被其他程序占用\n\n建议: {}",
+                err.hint()
+            )
+        );
+    }
+
+    #[test]
+    fn config_error_names_the_file() {
+        let err = ConfigError::IncludeCycle {
+            path: PathBuf::from("team.toml"),
+        };
+        assert_eq!(err.path(), Some(Path::new("team.toml")));
+        let err = ConfigError::Key {
+            key: "x".into(),
+            message: "未知的配置项".into(),
+        };
+        assert_eq!(err.path(), None);
+    }
+}
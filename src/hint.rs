@@ -0,0 +1,154 @@
+//! 组合键提示窗口
+//!
+//! 等待第二个键时在屏幕下方居中显示一个小窗口，列出可以按的键。
+//! 窗口不获取焦点，按键仍然发给原来的窗口。显示的文字由窗口用户数据（GWLP_USERDATA）持有。
+
+use windows::core::w;
+use windows::Win32::Foundation::{COLORREF, HWND, LPARAM, LRESULT, RECT, WPARAM};
+use windows::Win32::Graphics::Gdi::{
+    BeginPaint, CreateSolidBrush, DeleteObject, DrawTextW, EndPaint, FillRect, GetDC,
+    GetStockObject, InvalidateRect, ReleaseDC, SelectObject, SetBkMode, SetTextColor,
+    DEFAULT_GUI_FONT, DT_CALCRECT, DT_LEFT, DT_NOPREFIX, PAINTSTRUCT, TRANSPARENT,
+};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DestroyWindow, GetClientRect, GetSystemMetrics,
+    GetWindowLongPtrW, RegisterClassW, SetWindowLongPtrW, SetWindowPos, ShowWindow, GWLP_USERDATA,
+    HWND_TOPMOST, SM_CXSCREEN, SM_CYSCREEN, SWP_NOACTIVATE, SW_HIDE, SW_SHOWNOACTIVATE, WM_PAINT,
+    WNDCLASSW, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW, WS_EX_TOPMOST, WS_POPUP,
+};
+
+/// 文字与窗口边缘的距离
+const PADDING: i32 = 12;
+
+/// 背景色和文字颜色（0x00BBGGRR）
+const BACKGROUND: COLORREF = COLORREF(0x00302C2B);
+const FOREGROUND: COLORREF = COLORREF(0x00F0F0F0);
+
+/// 提示窗口，销毁时关闭
+pub struct Hint {
+    hwnd: HWND,
+}
+
+impl Hint {
+    /// 创建（隐藏的）提示窗口，失败时返回 `None`，组合键照常工作
+    pub fn create() -> Option<Self> {
+        unsafe {
+            let instance = GetModuleHandleW(None).ok()?;
+            let class_name = w!("WindowTopMostHint");
+            let wc = WNDCLASSW {
+                lpfnWndProc: Some(window_proc),
+                hInstance: instance.into(),
+                lpszClassName: class_name,
+                ..Default::default()
+            };
+            RegisterClassW(&wc);
+
+            let hwnd = CreateWindowExW(
+                WS_EX_TOPMOST | WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE,
+                class_name,
+                w!("WindowTopMostHint"),
+                WS_POPUP,
+                0,
+                0,
+                0,
+                0,
+                None,
+                None,
+                instance,
+                None,
+            )
+            .ok()?;
+            // 正在显示的文字（UTF-16）
+            let text = Box::into_raw(Box::<Vec<u16>>::default());
+            SetWindowLongPtrW(hwnd, GWLP_USERDATA, text as isize);
+            Some(Self { hwnd })
+        }
+    }
+
+    /// 显示提示，大小随文字调整
+    pub fn show(&self, text: &str) {
+        let mut wide: Vec<u16> = text.encode_utf16().collect();
+        unsafe {
+            // 按文字计算窗口大小
+            let mut rect = RECT::default();
+            let hdc = GetDC(self.hwnd);
+            let old = SelectObject(hdc, GetStockObject(DEFAULT_GUI_FONT));
+            DrawTextW(hdc, &mut wide, &mut rect, DT_CALCRECT | DT_NOPREFIX);
+            SelectObject(hdc, old);
+            ReleaseDC(self.hwnd, hdc);
+
+            let width = rect.right - rect.left + PADDING * 2;
+            let height = rect.bottom - rect.top + PADDING * 2;
+            let x = (GetSystemMetrics(SM_CXSCREEN) - width) / 2;
+            let y = GetSystemMetrics(SM_CYSCREEN) * 3 / 4 - height / 2;
+
+            if let Some(text) = text_mut(self.hwnd) {
+                *text = wide;
+            }
+            let _ = SetWindowPos(self.hwnd, HWND_TOPMOST, x, y, width, height, SWP_NOACTIVATE);
+            let _ = InvalidateRect(self.hwnd, None, true);
+            let _ = ShowWindow(self.hwnd, SW_SHOWNOACTIVATE);
+        }
+    }
+
+    /// 隐藏提示
+    pub fn hide(&self) {
+        unsafe {
+            let _ = ShowWindow(self.hwnd, SW_HIDE);
+        }
+    }
+}
+
+impl Drop for Hint {
+    fn drop(&mut self) {
+        unsafe {
+            let text = SetWindowLongPtrW(self.hwnd, GWLP_USERDATA, 0);
+            if text != 0 {
+                drop(Box::from_raw(text as *mut Vec<u16>));
+            }
+            let _ = DestroyWindow(self.hwnd);
+        }
+    }
+}
+
+/// 提示窗口持有的文字
+unsafe fn text_mut<'a>(hwnd: HWND) -> Option<&'a mut Vec<u16>> {
+    (GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut Vec<u16>).as_mut()
+}
+
+/// 窗口过程：只负责绘制
+unsafe extern "system" fn window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if msg != WM_PAINT {
+        return DefWindowProcW(hwnd, msg, wparam, lparam);
+    }
+
+    let mut ps = PAINTSTRUCT::default();
+    let hdc = BeginPaint(hwnd, &mut ps);
+    let mut client = RECT::default();
+    let _ = GetClientRect(hwnd, &mut client);
+    let brush = CreateSolidBrush(BACKGROUND);
+    FillRect(hdc, &client, brush);
+    let _ = DeleteObject(brush);
+
+    SetBkMode(hdc, TRANSPARENT);
+    SetTextColor(hdc, FOREGROUND);
+    let old = SelectObject(hdc, GetStockObject(DEFAULT_GUI_FONT));
+    if let Some(text) = text_mut(hwnd) {
+        let mut rect = RECT {
+            left: client.left + PADDING,
+            top: client.top + PADDING,
+            right: client.right - PADDING,
+            bottom: client.bottom - PADDING,
+        };
+        DrawTextW(hdc, text, &mut rect, DT_LEFT | DT_NOPREFIX);
+    }
+    SelectObject(hdc, old);
+    let _ = EndPaint(hwnd, &ps);
+    LRESULT(0)
+}
@@ -1,33 +1,64 @@
-//! 全局快捷键管理模块
+//! 全局快捷键注册 (Win32)
 
-use crate::config::HotkeyConfig;
-use anyhow::{anyhow, Result};
+use window_topmost::accelerator::{Side, Sides};
+use window_topmost::chord::ChordKeys;
+use window_topmost::keys::MOD_NOREPEAT;
+use window_topmost::{Accelerator, HotkeyError, HotkeyManager, Registrar};
+use windows::Win32::Foundation::{ERROR_HOTKEY_ALREADY_REGISTERED, HWND};
 use windows::Win32::UI::Input::KeyboardAndMouse::{
-    RegisterHotKey, UnregisterHotKey, HOT_KEY_MODIFIERS,
+    GetAsyncKeyState, RegisterHotKey, UnregisterHotKey, HOT_KEY_MODIFIERS, VIRTUAL_KEY,
+    VK_LCONTROL, VK_LMENU, VK_LSHIFT, VK_RCONTROL, VK_RMENU, VK_RSHIFT,
 };
 
-/// 快捷键 ID
-const HOTKEY_ID: i32 = 1;
+/// 托盘窗口使用的快捷键管理器
+pub type Hotkeys = HotkeyManager<Win32Registrar>;
 
-/// 注册全局快捷键
-pub fn register_hotkey(config: &HotkeyConfig) -> Result<i32> {
-    let vk_code = config
-        .get_vk_code()
-        .ok_or_else(|| anyhow!("无效的快捷键: {}", config.key))?;
+/// 托盘窗口使用的组合键
+pub type Chords = ChordKeys<Win32Registrar>;
 
-    let modifiers = HOT_KEY_MODIFIERS(config.get_modifiers());
+/// 用 RegisterHotKey 注册，WM_HOTKEY 发往 `hwnd`
+pub struct Win32Registrar {
+    pub hwnd: HWND,
+}
+
+impl Registrar for Win32Registrar {
+    fn register(&mut self, id: i32, accel: &Accelerator, vk: u32) -> Result<(), HotkeyError> {
+        // 防止按住时重复触发
+        let modifiers = HOT_KEY_MODIFIERS(accel.modifiers() | MOD_NOREPEAT);
 
-    unsafe {
-        RegisterHotKey(None, HOTKEY_ID, modifiers, vk_code)
-            .map_err(|e| anyhow!("注册快捷键失败: {}。\n可能被其他程序占用。", e))?;
+        unsafe {
+            RegisterHotKey(self.hwnd, id, modifiers, vk).map_err(|e| {
+                let hotkey = accel.label();
+                if e.code() == ERROR_HOTKEY_ALREADY_REGISTERED.to_hresult() {
+                    HotkeyError::Conflict { hotkey }
+                } else {
+                    HotkeyError::Register {
+                        hotkey,
+                        source: e.into(),
+                    }
+                }
+            })
+        }
     }
 
-    Ok(HOTKEY_ID)
+    fn unregister(&mut self, id: i32) {
+        unsafe {
+            let _ = UnregisterHotKey(self.hwnd, id);
+        }
+    }
 }
 
-/// 注销全局快捷键
-pub fn unregister_hotkey(id: i32) {
-    unsafe {
-        let _ = UnregisterHotKey(None, id);
-    }
+/// 限定左右的修饰键是否按在了对应的一侧
+///
+/// RegisterHotKey 不区分左右，收到 WM_HOTKEY 时再检查。
+pub fn sides_down(sides: &Sides) -> bool {
+    let down = |vk: VIRTUAL_KEY| unsafe { GetAsyncKeyState(vk.0 as i32) } < 0;
+    let check = |side: Side, left, right| match side {
+        Side::Either => true,
+        Side::Left => down(left),
+        Side::Right => down(right),
+    };
+    check(sides.ctrl, VK_LCONTROL, VK_RCONTROL)
+        && check(sides.alt, VK_LMENU, VK_RMENU)
+        && check(sides.shift, VK_LSHIFT, VK_RSHIFT)
 }
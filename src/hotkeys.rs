@@ -0,0 +1,629 @@
+//! 快捷键注册管理
+//!
+//! [`HotkeyManager`] 分配 WM_HOTKEY 的 ID、记录 ID 对应的动作，并负责注销。
+//! 系统调用通过 [`Registrar`] 完成，因此这里的逻辑与平台无关，可以直接测试。
+//!
+//! 每个绑定依次尝试首选组合和备选组合；全部被其他程序占用的绑定留待 [`HotkeyManager::retry`] 重试。
+
+use crate::accelerator::{Accelerator, Sides};
+use crate::action::Action;
+use crate::chord::LEADER_IDS;
+use crate::config::Binding;
+use crate::error::HotkeyError;
+
+/// 最大快捷键 ID（Win32 规定应用程序使用 0x0000-0xBFFF，其中从 [`LEADER_IDS`] 开始的留给组合键）
+const MAX_ID: i32 = LEADER_IDS - 1;
+
+/// 系统快捷键注册接口，Windows 上为 RegisterHotKey / UnregisterHotKey
+pub trait Registrar {
+    /// 注册快捷键，`vk` 为已经换算好的虚拟键码；失败时的错误信息使用 `accel`
+    fn register(&mut self, id: i32, accel: &Accelerator, vk: u32) -> Result<(), HotkeyError>;
+
+    /// 注销快捷键
+    fn unregister(&mut self, id: i32);
+}
+
+/// 已向系统注册的组合
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Combo {
+    id: i32,
+    modifiers: u32,
+    vk: u32,
+}
+
+/// 注册成功的绑定
+#[derive(Debug, Clone)]
+struct Entry {
+    id: i32,
+    accel: Accelerator,
+    binding: Binding,
+}
+
+/// 一个绑定的注册结果，成功时为实际注册的组合
+#[derive(Debug)]
+pub struct Outcome {
+    pub binding: Binding,
+    pub result: Result<Accelerator, HotkeyError>,
+}
+
+impl Outcome {
+    /// 首选组合不可用、改用的备选组合
+    pub fn fallback(&self) -> Option<Accelerator> {
+        let used = *self.result.as_ref().ok()?;
+        (self.binding.hotkey.accelerator().ok() != Some(used)).then_some(used)
+    }
+}
+
+/// 一组绑定的注册结果
+#[derive(Debug, Default)]
+pub struct Report {
+    pub outcomes: Vec<Outcome>,
+}
+
+impl Report {
+    /// 全部注册成功
+    pub fn is_ok(&self) -> bool {
+        self.outcomes.iter().all(|o| o.result.is_ok())
+    }
+
+    /// 注册成功的个数
+    pub fn registered(&self) -> usize {
+        self.outcomes.iter().filter(|o| o.result.is_ok()).count()
+    }
+
+    /// 注册失败的绑定及原因
+    pub fn failures(&self) -> impl Iterator<Item = (&Binding, &HotkeyError)> {
+        self.outcomes
+            .iter()
+            .filter_map(|o| o.result.as_ref().err().map(|e| (&o.binding, e)))
+    }
+
+    /// 有被占用、留待重试的绑定
+    pub fn blocked(&self) -> bool {
+        self.failures().any(|(_, err)| err.is_blocked())
+    }
+
+    /// 失败列表，每行一个：`快捷键（动作）: 原因`
+    pub fn failure_lines(&self) -> String {
+        self.failures()
+            .map(|(binding, err)| {
+                format!(
+                    "{}（{}）: {}",
+                    binding.hotkey.display(),
+                    binding.action.name(),
+                    err
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// 改用备选组合的绑定，每行一个：`快捷键（动作）→ 备选组合`
+    pub fn fallback_lines(&self) -> String {
+        self.outcomes
+            .iter()
+            .filter_map(|o| {
+                let used = o.fallback()?;
+                Some(format!(
+                    "{}（{}）→ {}",
+                    o.binding.hotkey.display(),
+                    o.binding.action.name(),
+                    used.label()
+                ))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// 注册成功的列表，每行一个：`组合（动作）`
+    pub fn registered_lines(&self) -> String {
+        self.outcomes
+            .iter()
+            .filter_map(|o| {
+                let used = o.result.as_ref().ok()?;
+                Some(format!("{}（{}）", used.label(), o.binding.action.name()))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// 快捷键管理器，销毁时注销全部快捷键
+///
+/// 发布版本 panic 时直接终止进程（`panic = "abort"`），不会执行销毁；
+/// 这时由系统在进程退出时释放它注册的全部快捷键，同样不会残留。
+pub struct HotkeyManager<R: Registrar> {
+    registrar: R,
+    combos: Vec<Combo>,
+    entries: Vec<Entry>,
+    /// 全部候选组合都被占用的绑定
+    pending: Vec<Binding>,
+}
+
+impl<R: Registrar> HotkeyManager<R> {
+    pub fn new(registrar: R) -> Self {
+        Self {
+            registrar,
+            combos: Vec::new(),
+            entries: Vec::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// 系统注册接口
+    pub fn registrar(&self) -> &R {
+        &self.registrar
+    }
+
+    /// 注册一组绑定，失败的跳过，返回每个绑定的结果
+    ///
+    /// 只是左右不同的绑定（如 LCtrl+T 和 RCtrl+T）共用一次注册。被占用的绑定记下来，
+    /// 由 [`retry`](Self::retry) 重试。
+    pub fn register_all(&mut self, bindings: &[Binding]) -> Report {
+        let outcomes: Vec<Outcome> = bindings
+            .iter()
+            .map(|binding| Outcome {
+                binding: binding.clone(),
+                result: self.register(binding),
+            })
+            .collect();
+        for outcome in &outcomes {
+            if outcome.result.as_ref().is_err_and(HotkeyError::is_blocked) {
+                self.pending.push(outcome.binding.clone());
+            }
+        }
+        Report { outcomes }
+    }
+
+    /// 依次尝试候选组合，与本程序其他绑定相同的组合也跳过
+    ///
+    /// 全部失败时返回首选组合的错误；有候选被其他程序占用时返回被占用的错误，以便重试。
+    fn register(&mut self, binding: &Binding) -> Result<Accelerator, HotkeyError> {
+        let mut error: Option<HotkeyError> = None;
+        for accel in binding.hotkey.candidates()? {
+            match self.register_combo(binding, accel) {
+                Ok(()) => return Ok(accel),
+                Err(err) => {
+                    if error
+                        .as_ref()
+                        .is_none_or(|e| !e.is_blocked() && err.is_blocked())
+                    {
+                        error = Some(err);
+                    }
+                }
+            }
+        }
+        Err(error.expect("至少有一个候选组合"))
+    }
+
+    fn register_combo(&mut self, binding: &Binding, accel: Accelerator) -> Result<(), HotkeyError> {
+        let vk = match accel.key.vk_code() {
+            0 => {
+                return Err(HotkeyError::InvalidKey {
+                    key: accel.key.name(),
+                })
+            }
+            vk => vk,
+        };
+        let modifiers = accel.modifiers();
+
+        let existing = self
+            .combos
+            .iter()
+            .find(|c| (c.modifiers, c.vk) == (modifiers, vk));
+        // 只是左右不同的绑定共用这个 ID，同一次按键会触发的绑定只有第一个有效
+        if let Some(combo) = existing {
+            let same = self
+                .entries
+                .iter()
+                .find(|e| e.id == combo.id && e.accel.overlaps(&accel));
+            if let Some(entry) = same {
+                return Err(HotkeyError::Duplicate {
+                    hotkey: accel.label(),
+                    action: entry.binding.action.name().to_string(),
+                });
+            }
+        }
+        let id = match existing {
+            Some(combo) => combo.id,
+            None => {
+                let id = self.free_id().ok_or_else(|| HotkeyError::Register {
+                    hotkey: accel.label(),
+                    source: std::io::Error::other("快捷键数量超过上限"),
+                })?;
+                self.registrar.register(id, &accel, vk)?;
+                self.combos.push(Combo { id, modifiers, vk });
+                id
+            }
+        };
+        self.entries.push(Entry {
+            id,
+            accel,
+            binding: binding.clone(),
+        });
+        Ok(())
+    }
+
+    /// 最小的未使用的 ID
+    fn free_id(&self) -> Option<i32> {
+        (1..=MAX_ID).find(|id| self.combos.iter().all(|c| c.id != *id))
+    }
+
+    /// 重新注册被占用的绑定，返回这次注册成功的绑定；仍被占用的继续等待
+    pub fn retry(&mut self) -> Report {
+        let pending = std::mem::take(&mut self.pending);
+        let mut report = self.register_all(&pending);
+        report.outcomes.retain(|o| o.result.is_ok());
+        report
+    }
+
+    /// 等待重试的绑定
+    pub fn pending(&self) -> &[Binding] {
+        &self.pending
+    }
+
+    /// 用新的一组绑定替换全部快捷键
+    ///
+    /// 失败的只有被占用或重复的绑定时照常替换（与启动时一样），被占用的留待重试；
+    /// 有其他错误时恢复原来的快捷键，返回新绑定的注册结果。
+    pub fn replace_all(&mut self, bindings: &[Binding]) -> Result<Report, Report> {
+        let old: Vec<Binding> = self
+            .entries
+            .iter()
+            .map(|e| e.binding.clone())
+            .chain(self.pending.iter().cloned())
+            .collect();
+        // 先注销旧快捷键，新配置可能沿用相同的组合
+        self.clear();
+        let report = self.register_all(bindings);
+        let tolerated =
+            |err: &HotkeyError| err.is_blocked() || matches!(err, HotkeyError::Duplicate { .. });
+        if report.failures().all(|(_, err)| tolerated(err)) {
+            return Ok(report);
+        }
+        self.clear();
+        self.register_all(&old);
+        Err(report)
+    }
+
+    /// 注销全部快捷键
+    pub fn clear(&mut self) {
+        for combo in self.combos.drain(..) {
+            self.registrar.unregister(combo.id);
+        }
+        self.entries.clear();
+        self.pending.clear();
+    }
+
+    /// 收到 WM_HOTKEY 时对应的动作；`pressed` 检查限定左右的修饰键是否按在对应的一侧
+    pub fn action(&self, id: i32, pressed: impl Fn(&Sides) -> bool) -> Option<Action> {
+        self.entries
+            .iter()
+            .find(|e| e.id == id && pressed(&e.accel.sides))
+            .map(|e| e.binding.action)
+    }
+
+    /// 占用了 `accel` 这个组合（不论左右）的绑定，本程序不能再用它注册别的快捷键
+    pub fn binding_for(&self, accel: &Accelerator) -> Option<&Binding> {
+        self.entries
+            .iter()
+            .find(|e| {
+                e.accel.modifiers() == accel.modifiers()
+                    && e.accel.key.vk_code() == accel.key.vk_code()
+            })
+            .map(|e| &e.binding)
+    }
+
+    /// 已注册的绑定
+    pub fn bindings(&self) -> impl Iterator<Item = &Binding> {
+        self.entries.iter().map(|e| &e.binding)
+    }
+}
+
+impl<R: Registrar> Drop for HotkeyManager<R> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accelerator::Side;
+    use crate::config::HotkeyConfig;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// 模拟的系统快捷键表：`taken` 中的组合已被其他程序占用
+    #[derive(Default)]
+    struct System {
+        taken: Vec<(u32, u32)>,
+        registered: Vec<(i32, u32, u32)>,
+    }
+
+    #[derive(Clone, Default)]
+    struct SimRegistrar(Rc<RefCell<System>>);
+
+    impl Registrar for SimRegistrar {
+        fn register(&mut self, id: i32, accel: &Accelerator, vk: u32) -> Result<(), HotkeyError> {
+            let mut system = self.0.borrow_mut();
+            let combo = (accel.modifiers(), vk);
+            let used = system.registered.iter().any(|r| (r.1, r.2) == combo);
+            if used || system.taken.contains(&combo) {
+                return Err(HotkeyError::Conflict {
+                    hotkey: accel.label(),
+                });
+            }
+            system.registered.push((id, combo.0, combo.1));
+            Ok(())
+        }
+
+        fn unregister(&mut self, id: i32) {
+            self.0.borrow_mut().registered.retain(|r| r.0 != id);
+        }
+    }
+
+    impl SimRegistrar {
+        fn take(&self, hotkey: &str) {
+            let accel: Accelerator = hotkey.parse().unwrap();
+            let combo = (accel.modifiers(), accel.key.vk_code());
+            self.0.borrow_mut().taken.push(combo);
+        }
+
+        /// 其他程序释放了组合
+        fn release(&self, hotkey: &str) {
+            let accel: Accelerator = hotkey.parse().unwrap();
+            let combo = (accel.modifiers(), accel.key.vk_code());
+            self.0.borrow_mut().taken.retain(|c| *c != combo);
+        }
+
+        fn ids(&self) -> Vec<i32> {
+            self.0.borrow().registered.iter().map(|r| r.0).collect()
+        }
+    }
+
+    fn binding(hotkey: &str, action: Action) -> Binding {
+        Binding {
+            hotkey: hotkey.parse::<Accelerator>().unwrap().into(),
+            action,
+        }
+    }
+
+    fn with_fallbacks(hotkeys: &[&str], action: Action) -> Binding {
+        let value = toml::Value::from(hotkeys.to_vec());
+        Binding {
+            hotkey: value.try_into().unwrap(),
+            action,
+        }
+    }
+
+    fn any_side(_: &Sides) -> bool {
+        true
+    }
+
+    #[test]
+    fn allocates_ids_and_maps_actions() {
+        let system = SimRegistrar::default();
+        let mut manager = HotkeyManager::new(system.clone());
+        let report = manager.register_all(&[
+            binding("Ctrl+Space", Action::Toggle),
+            binding("F9", Action::UnpinAll),
+        ]);
+        assert!(report.is_ok());
+        assert_eq!(system.ids(), vec![1, 2]);
+        assert_eq!(manager.action(1, any_side), Some(Action::Toggle));
+        assert_eq!(manager.action(2, any_side), Some(Action::UnpinAll));
+        assert_eq!(manager.action(3, any_side), None);
+    }
+
+    #[test]
+    fn reports_each_failure_and_keeps_the_rest() {
+        let system = SimRegistrar::default();
+        system.take("F9");
+        let mut manager = HotkeyManager::new(system.clone());
+        let report = manager.register_all(&[
+            binding("F9", Action::UnpinAll),
+            binding("Ctrl+Space", Action::Toggle),
+            binding("F9", Action::Exit),
+        ]);
+        assert_eq!(report.registered(), 1);
+        assert_eq!(report.failures().count(), 2);
+        assert_eq!(
+            report.failure_lines(),
+            "F9（unpin_all）: 快捷键 F9 已被其他程序占用\nF9（exit）: 快捷键 F9 已被其他程序占用"
+        );
+        assert_eq!(system.ids(), vec![1]);
+        assert_eq!(manager.action(1, any_side), Some(Action::Toggle));
+    }
+
+    #[test]
+    fn left_and_right_share_one_registration() {
+        let system = SimRegistrar::default();
+        let mut manager = HotkeyManager::new(system.clone());
+        let report = manager.register_all(&[
+            binding("LCtrl+T", Action::Pin),
+            binding("RCtrl+T", Action::Unpin),
+        ]);
+        assert!(report.is_ok());
+        assert_eq!(system.ids(), vec![1]);
+        let right = |sides: &Sides| sides.ctrl != Side::Left;
+        assert_eq!(manager.action(1, right), Some(Action::Unpin));
+        let plain: Accelerator = "Ctrl+T".parse().unwrap();
+        let binding = manager.binding_for(&plain).map(|b| b.action);
+        assert_eq!(binding, Some(Action::Pin));
+    }
+
+    #[test]
+    fn identical_bindings_are_reported() {
+        let system = SimRegistrar::default();
+        let mut manager = HotkeyManager::new(system.clone());
+        let report = manager.register_all(&[
+            binding("Ctrl+T", Action::Pin),
+            binding("Ctrl+T", Action::Unpin),
+            binding("LCtrl+T", Action::Exit),
+        ]);
+        assert_eq!(report.registered(), 1);
+        assert_eq!(
+            report.failure_lines(),
+            "Ctrl+T（unpin）: 快捷键 Ctrl+T 已经绑定到动作 pin\n\
+             LCtrl+T（exit）: 快捷键 LCtrl+T 已经绑定到动作 pin"
+        );
+        // 不是被其他程序占用，不会重试
+        assert!(!report.blocked());
+        assert!(manager.pending().is_empty());
+        assert_eq!(system.ids(), vec![1]);
+        assert_eq!(manager.action(1, any_side), Some(Action::Pin));
+    }
+
+    #[test]
+    fn replace_all_is_all_or_nothing() {
+        let system = SimRegistrar::default();
+        let mut manager = HotkeyManager::new(system.clone());
+        manager.register_all(&[binding("Ctrl+Space", Action::Toggle)]);
+
+        // 新配置沿用相同的组合也能成功
+        let report = manager
+            .replace_all(&[
+                binding("Ctrl+Space", Action::Pin),
+                binding("F8", Action::Unpin),
+            ])
+            .unwrap();
+        assert_eq!(report.registered(), 2);
+        assert_eq!(manager.action(1, any_side), Some(Action::Pin));
+
+        // 有一个无效时保留原来的快捷键
+        let invalid = Binding {
+            hotkey: HotkeyConfig {
+                key: "Spcae".into(),
+                ..Default::default()
+            },
+            action: Action::Exit,
+        };
+        let report = manager
+            .replace_all(&[binding("Ctrl+Alt+T", Action::Toggle), invalid])
+            .unwrap_err();
+        assert_eq!(report.failures().count(), 1);
+        let actions: Vec<Action> = manager.bindings().map(|b| b.action).collect();
+        assert_eq!(actions, vec![Action::Pin, Action::Unpin]);
+        assert_eq!(system.ids().len(), 2);
+    }
+
+    #[test]
+    fn reloading_a_config_with_a_duplicate_succeeds() {
+        let system = SimRegistrar::default();
+        let mut manager = HotkeyManager::new(system.clone());
+        let bindings = [
+            binding("Ctrl+Space", Action::Toggle),
+            binding("Ctrl+Space", Action::Exit),
+            binding("F8", Action::Pin),
+        ];
+        let report = manager.register_all(&bindings);
+        assert_eq!(report.failures().count(), 1);
+
+        // 同一个配置重新加载多少次都照常替换
+        for _ in 0..2 {
+            let report = manager.replace_all(&bindings).unwrap();
+            assert_eq!(
+                report.failure_lines(),
+                "Ctrl+Space（exit）: 快捷键 Ctrl+Space 已经绑定到动作 toggle"
+            );
+            assert_eq!(system.ids(), vec![1, 2]);
+            assert_eq!(manager.action(1, any_side), Some(Action::Toggle));
+        }
+    }
+
+    #[test]
+    fn falls_back_to_the_next_free_candidate() {
+        let system = SimRegistrar::default();
+        system.take("Ctrl+Space");
+        let mut manager = HotkeyManager::new(system.clone());
+        let report = manager.register_all(&[with_fallbacks(
+            &["Ctrl+Space", "Ctrl+Alt+Space", "F9"],
+            Action::Toggle,
+        )]);
+        assert!(report.is_ok());
+        assert_eq!(
+            report.fallback_lines(),
+            "Ctrl+Space（toggle）→ Ctrl+Alt+Space"
+        );
+        assert!(manager.pending().is_empty());
+        assert_eq!(manager.action(1, any_side), Some(Action::Toggle));
+    }
+
+    #[test]
+    fn falls_back_when_the_primary_is_our_own_binding() {
+        let system = SimRegistrar::default();
+        system.take("F9");
+        let mut manager = HotkeyManager::new(system.clone());
+        let report = manager.register_all(&[
+            binding("Ctrl+Space", Action::Toggle),
+            with_fallbacks(&["Ctrl+Space", "Ctrl+Alt+Space"], Action::UnpinAll),
+            with_fallbacks(&["Ctrl+Space", "F9"], Action::Exit),
+        ]);
+        assert_eq!(
+            report.fallback_lines(),
+            "Ctrl+Space（unpin_all）→ Ctrl+Alt+Space"
+        );
+        assert_eq!(manager.action(2, any_side), Some(Action::UnpinAll));
+
+        // 备选组合被占用时报告占用并重试
+        assert_eq!(
+            report.failure_lines(),
+            "Ctrl+Space（exit）: 快捷键 F9 已被其他程序占用"
+        );
+        assert_eq!(manager.pending().len(), 1);
+        system.release("F9");
+        assert_eq!(manager.retry().registered_lines(), "F9（exit）");
+    }
+
+    #[test]
+    fn blocked_bindings_are_retried() {
+        let system = SimRegistrar::default();
+        system.take("F9");
+        system.take("Ctrl+F9");
+        let mut manager = HotkeyManager::new(system.clone());
+        let report = manager.register_all(&[
+            binding("Ctrl+Space", Action::Toggle),
+            with_fallbacks(&["F9", "Ctrl+F9"], Action::UnpinAll),
+        ]);
+        assert!(report.blocked());
+        assert_eq!(
+            report.failure_lines(),
+            "F9（unpin_all）: 快捷键 F9 已被其他程序占用"
+        );
+        assert_eq!(manager.pending().len(), 1);
+
+        // 还被占用时继续等待
+        assert_eq!(manager.retry().registered(), 0);
+        assert_eq!(manager.pending().len(), 1);
+
+        system.release("Ctrl+F9");
+        let report = manager.retry();
+        assert_eq!(report.registered_lines(), "Ctrl+F9（unpin_all）");
+        assert!(manager.pending().is_empty());
+        assert_eq!(manager.action(2, any_side), Some(Action::UnpinAll));
+
+        // 重新加载时被占用的绑定不阻止替换
+        system.take("F8");
+        let report = manager
+            .replace_all(&[binding("F8", Action::Pin), binding("F7", Action::Unpin)])
+            .unwrap();
+        assert!(report.blocked());
+        assert_eq!(manager.pending().len(), 1);
+        assert_eq!(system.ids().len(), 1);
+    }
+
+    /// panic 展开时（调试版本和库的使用者）也会注销；发布版本见 [`HotkeyManager`] 的说明
+    #[test]
+    fn unregisters_everything_on_drop() {
+        let system = SimRegistrar::default();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let mut manager = HotkeyManager::new(system.clone());
+            manager.register_all(&[binding("F8", Action::Pin), binding("F9", Action::Unpin)]);
+            assert_eq!(system.ids().len(), 2);
+            panic!("模拟崩溃");
+        }));
+        assert!(result.is_err());
+        assert!(system.ids().is_empty());
+    }
+}
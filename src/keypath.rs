@@ -0,0 +1,311 @@
+//! 配置项路径
+//!
+//! 命令行 `config get` / `config set` 用路径指定配置项，例如 `hotkey`、`hotkey.key`、
+//! `settings.play_sound`、`bindings[0].action`。
+
+use crate::config::{Config, HotkeyConfig};
+use crate::edit;
+use crate::error::ConfigError;
+use crate::layers::Layered;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use toml::{Table, Value};
+use toml_edit::DocumentMut;
+
+/// 路径中的一段
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    /// 表中的字段
+    Field(String),
+    /// 数组下标
+    Index(usize),
+}
+
+fn key_error(key: &str, message: impl Into<String>) -> ConfigError {
+    ConfigError::Key {
+        key: key.to_string(),
+        message: message.into(),
+    }
+}
+
+/// 解析 `a.b[0].c` 形式的路径
+fn parse(key: &str) -> Result<Vec<Segment>, ConfigError> {
+    let mut segments = Vec::new();
+    for part in key.split('.') {
+        let (name, mut rest) = part.split_at(part.find('[').unwrap_or(part.len()));
+        if name.is_empty() {
+            return Err(key_error(key, "缺少字段名"));
+        }
+        segments.push(Segment::Field(name.to_string()));
+        while !rest.is_empty() {
+            let index = rest
+                .strip_prefix('[')
+                .and_then(|r| r.split_once(']'))
+                .and_then(|(index, r)| Some((index.parse().ok()?, r)));
+            let Some((index, r)) = index else {
+                return Err(key_error(key, "下标应写成 [数字]"));
+            };
+            segments.push(Segment::Index(index));
+            rest = r;
+        }
+    }
+    Ok(segments)
+}
+
+/// 按路径找到值
+fn lookup<'a>(
+    mut value: &'a mut Value,
+    segments: &[Segment],
+    key: &str,
+) -> Result<&'a mut Value, ConfigError> {
+    for segment in segments {
+        value = match (segment, value) {
+            (Segment::Field(name), Value::Table(table)) => table
+                .get_mut(name)
+                .ok_or_else(|| key_error(key, format!("没有字段 {}", name)))?,
+            (Segment::Index(i), Value::Array(array)) => {
+                let len = array.len();
+                array.get_mut(*i).ok_or_else(|| {
+                    key_error(key, format!("下标 {} 超出范围（共 {} 项）", i, len))
+                })?
+            }
+            _ => return Err(key_error(key, "路径与配置的结构不符")),
+        };
+    }
+    Ok(value)
+}
+
+fn to_value(config: &Config) -> Value {
+    Value::try_from(config).expect("配置总能转换为 TOML")
+}
+
+/// 值为快捷键的字段
+const HOTKEY_FIELDS: [&str; 2] = ["hotkey", "leader"];
+
+/// 快捷键表换成 `Ctrl+Alt+T` 形式的字符串
+fn hotkeys_as_strings(value: &mut Value) {
+    match value {
+        Value::Table(table) => {
+            for (name, field) in table.iter_mut() {
+                if HOTKEY_FIELDS.contains(&name.as_str()) {
+                    if let Ok(hotkey) = HotkeyConfig::deserialize(field.clone()) {
+                        *field = hotkey.to_toml();
+                        continue;
+                    }
+                }
+                hotkeys_as_strings(field);
+            }
+        }
+        Value::Array(array) => array.iter_mut().for_each(hotkeys_as_strings),
+        _ => {}
+    }
+}
+
+/// 读取配置项；快捷键显示为 `Ctrl+Alt+T` 形式，有备选组合时为数组
+pub fn get(config: &Config, key: &str) -> Result<Value, ConfigError> {
+    let segments = parse(key)?;
+    let mut root = to_value(config);
+    let mut value = lookup(&mut root, &segments, key)?.clone();
+    let is_hotkey = match segments.last() {
+        Some(Segment::Field(name)) => HOTKEY_FIELDS.contains(&name.as_str()),
+        _ => false,
+    };
+    if is_hotkey {
+        value = HotkeyConfig::deserialize(value)
+            .map_or_else(|e| Value::String(e.to_string()), |hotkey| hotkey.to_toml());
+    }
+    hotkeys_as_strings(&mut value);
+    Ok(value)
+}
+
+/// 命令行输出：字符串不加引号，表和数组输出为 JSON
+pub fn format(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Table(_) | Value::Array(_) => {
+            serde_json::to_string(value).expect("TOML 值总能转换为 JSON")
+        }
+        other => other.to_string(),
+    }
+}
+
+/// 把命令行上的值按 TOML 解析，`false`、`3`、`["a"]` 等；不是有效的 TOML 时作为字符串
+fn parse_value(raw: &str) -> Value {
+    toml::from_str::<Table>(&format!("value = {}", raw))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(raw.to_string()))
+}
+
+/// 修改配置项，`raw` 为命令行上的值；类型不对或快捷键无效时返回错误
+pub fn set(config: &mut Config, key: &str, raw: &str) -> Result<(), ConfigError> {
+    let segments = parse(key)?;
+    let mut root = to_value(config);
+    let (last, parents) = segments.split_last().expect("路径至少有一段");
+    let target = match (last, lookup(&mut root, parents, key)?) {
+        // 可以省略的字段（如 max_pinned）不在序列化结果里，允许新增，下面再检查
+        (Segment::Field(name), Value::Table(table)) => {
+            table.entry(name.clone()).or_insert(Value::Boolean(false))
+        }
+        (segment, parent) => lookup(parent, std::slice::from_ref(segment), key)?,
+    };
+    *target = parse_value(raw);
+
+    let updated: Config = Config::deserialize(root).map_err(|e| key_error(key, e.message()))?;
+    // 未知字段会被反序列化忽略，改完后必须还能读到
+    lookup(&mut to_value(&updated), &segments, key)?;
+    updated
+        .validate()
+        .map_err(|e| key_error(key, e.to_string()))?;
+    *config = updated;
+    Ok(())
+}
+
+/// 修改配置文件中的一项并写回，保留注释和格式
+///
+/// 只修改 `path` 这一个文件；`layered` 是当前生效的配置，文件中没有写快捷键时以生效的快捷键为基础，
+/// 修改后的文件还要符合管理员策略。
+pub fn set_in_file(
+    path: &Path,
+    key: &str,
+    raw: &str,
+    layered: &Layered,
+) -> Result<(), ConfigError> {
+    let mut original = Config::read_file(path, false)?;
+    let mut config = Config::parse(&original, path)?;
+
+    if key == "hotkey" || key.starts_with("hotkey.") {
+        let mut doc: DocumentMut = original.parse().expect("已经检查过的配置");
+        if !doc.contains_key("hotkey") {
+            let hotkey = &layered.config.hotkey;
+            edit::replace(doc.as_table_mut(), "hotkey", edit::hotkey_value(hotkey));
+            config.hotkey = hotkey.clone();
+            original = doc.to_string();
+        }
+    }
+
+    set(&mut config, key, raw)?;
+    let content = config.merge_into(&original, path)?;
+    Config::parse(&content, path)?;
+    if let Some(violation) = layered.policy.check(&content, path).into_iter().next() {
+        return Err(ConfigError::Policy {
+            path: path.to_path_buf(),
+            line: violation.line,
+            column: violation.column,
+            message: violation.message,
+        });
+    }
+    fs::write(path, content).map_err(|source| ConfigError::Write {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::Action;
+    use crate::policy::Policy;
+
+    fn config() -> Config {
+        Config::parse(
+            "hotkey = \"Ctrl+Alt+T\"\n\n[[bindings]]\nhotkey = \"F9\"\naction = \"unpin_all\"\n",
+            Path::new("config.toml"),
+        )
+        .unwrap()
+    }
+
+    fn get_str(config: &Config, key: &str) -> String {
+        format(&get(config, key).unwrap())
+    }
+
+    #[test]
+    fn gets_nested_values() {
+        let config = config();
+        assert_eq!(get_str(&config, "hotkey"), "Ctrl+Alt+T");
+        assert_eq!(get_str(&config, "hotkey.key"), "T");
+        assert_eq!(get_str(&config, "hotkey.shift"), "false");
+        assert_eq!(get_str(&config, "settings.play_sound"), "true");
+        assert_eq!(get_str(&config, "bindings[0].hotkey"), "F9");
+        assert_eq!(get_str(&config, "bindings[0].action"), "unpin_all");
+        assert_eq!(
+            get_str(&config, "bindings"),
+            r#"[{"hotkey":"F9","action":"unpin_all"}]"#
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_paths() {
+        let config = config();
+        for key in [
+            "colour",
+            "settings.volume",
+            "bindings[3]",
+            "hotkey[0]",
+            "a..b",
+        ] {
+            let err = get(&config, key).unwrap_err();
+            assert!(matches!(err, ConfigError::Key { .. }), "{key}: {err}");
+        }
+    }
+
+    #[test]
+    fn sets_values_with_validation() {
+        let mut config = config();
+        set(&mut config, "settings.play_sound", "false").unwrap();
+        assert!(!config.settings.play_sound);
+        set(&mut config, "settings.max_pinned", "3").unwrap();
+        assert_eq!(config.settings.max_pinned, Some(3));
+        set(&mut config, "hotkey.key", "F8").unwrap();
+        assert_eq!(config.hotkey.display(), "Ctrl+Alt+F8");
+        set(&mut config, "hotkey", "Win+Space").unwrap();
+        assert_eq!(config.hotkey.display(), "Win+Space");
+        set(&mut config, "hotkey", r#"["Win+Space", "Win+F9"]"#).unwrap();
+        assert_eq!(get_str(&config, "hotkey"), r#"["Win+Space","Win+F9"]"#);
+        set(&mut config, "hotkey", "Win+Space").unwrap();
+        set(&mut config, "bindings[0].action", "toggle").unwrap();
+        assert_eq!(config.bindings[0].action, Action::Toggle);
+
+        for (key, value) in [
+            ("settings.play_sound", "maybe"),
+            ("settings.volume", "3"),
+            ("hotkey.key", "NoSuchKey"),
+            ("bindings[0].action", "explode"),
+            ("bindings[1].action", "toggle"),
+        ] {
+            let err = set(&mut config, key, value).unwrap_err();
+            assert!(matches!(err, ConfigError::Key { .. }), "{key}: {err}");
+        }
+        assert!(!config.settings.play_sound);
+    }
+
+    #[test]
+    fn set_in_file_keeps_comments_and_follows_policy() {
+        let dir =
+            std::env::temp_dir().join(format!("window-topmost-keypath-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        fs::write(
+            &path,
+            "version = 2\n\n# 声音\n[settings]\nplay_sound = true # 默认\n",
+        )
+        .unwrap();
+        let policy =
+            Policy::parse("[settings]\nmax_pinned = 3\n", Path::new("policy.toml")).unwrap();
+        let layered = crate::layers::load(&path, None, policy).unwrap();
+
+        set_in_file(&path, "settings.play_sound", "false", &layered).unwrap();
+        set_in_file(&path, "hotkey.key", "F8", &layered).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            content,
+            "version = 2\nhotkey = \"Ctrl+F8\"\n\n# 声音\n[settings]\nplay_sound = false # 默认\n"
+        );
+
+        let err = set_in_file(&path, "settings.max_pinned", "5", &layered).unwrap_err();
+        assert!(matches!(err, ConfigError::Policy { .. }), "{err}");
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,496 @@
+//! 按键定义模块（与平台无关）
+//!
+//! 扫描码按键 `sc:0x35` 按物理位置指定，不受键盘布局影响；注册时才按当前布局换算为虚拟键码。
+
+use crate::platform;
+
+/// 修饰键标志（数值与 Win32 `MOD_*` 一致）
+pub const MOD_ALT: u32 = 0x0001;
+pub const MOD_CONTROL: u32 = 0x0002;
+pub const MOD_SHIFT: u32 = 0x0004;
+pub const MOD_WIN: u32 = 0x0008;
+pub const MOD_NOREPEAT: u32 = 0x4000;
+
+/// 功能键的最大编号
+const MAX_F: u8 = 24;
+
+/// 主键
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    /// 功能键 F1-F24
+    F(u8),
+    /// 字母键 A-Z（大写）
+    Letter(char),
+    /// 数字键 0-9
+    Digit(char),
+    Space,
+    Enter,
+    Tab,
+    Escape,
+    Backspace,
+    Delete,
+    Insert,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    PrintScreen,
+    Pause,
+    ScrollLock,
+    Up,
+    Down,
+    Left,
+    Right,
+    /// 数字键盘 0-9
+    Numpad(u8),
+    NumpadAdd,
+    NumpadSubtract,
+    NumpadMultiply,
+    NumpadDivide,
+    NumpadDecimal,
+    /// `` ` ``
+    Backquote,
+    /// `-`
+    Minus,
+    /// `=`
+    Equal,
+    /// `[`
+    BracketLeft,
+    /// `]`
+    BracketRight,
+    /// `\`
+    Backslash,
+    /// `;`
+    Semicolon,
+    /// `'`
+    Quote,
+    /// `,`
+    Comma,
+    /// `.`
+    Period,
+    /// `/`
+    Slash,
+    VolumeMute,
+    VolumeDown,
+    VolumeUp,
+    MediaPlayPause,
+    MediaStop,
+    MediaNext,
+    MediaPrevious,
+    BrowserBack,
+    BrowserForward,
+    BrowserRefresh,
+    BrowserStop,
+    BrowserSearch,
+    BrowserFavorites,
+    BrowserHome,
+    /// 没有名称的虚拟键码，写作 `vk:0x7C`
+    Vk(u8),
+    /// 扫描码（物理位置），写作 `sc:0x35`，扩展键为 `sc:0xE035`
+    Scan(u16),
+}
+
+/// 有固定名称的按键
+struct Named {
+    key: Key,
+    /// 规范名称
+    name: &'static str,
+    /// 其他写法（大写）
+    aliases: &'static [&'static str],
+    /// Windows 虚拟键码
+    vk: u8,
+    /// README 中的分组
+    group: &'static str,
+}
+
+const fn named(
+    group: &'static str,
+    key: Key,
+    name: &'static str,
+    aliases: &'static [&'static str],
+    vk: u8,
+) -> Named {
+    Named {
+        key,
+        name,
+        aliases,
+        vk,
+        group,
+    }
+}
+
+/// 按键表，按 README 中的顺序排列
+#[rustfmt::skip]
+const NAMED: &[Named] = &[
+    named("数字键盘", Key::NumpadAdd, "NumpadAdd", &["NUMADD"], 0x6B),
+    named("数字键盘", Key::NumpadSubtract, "NumpadSubtract", &["NUMSUBTRACT"], 0x6D),
+    named("数字键盘", Key::NumpadMultiply, "NumpadMultiply", &["NUMMULTIPLY"], 0x6A),
+    named("数字键盘", Key::NumpadDivide, "NumpadDivide", &["NUMDIVIDE"], 0x6F),
+    named("数字键盘", Key::NumpadDecimal, "NumpadDecimal", &["NUMDECIMAL"], 0x6E),
+
+    named("特殊键", Key::Space, "Space", &[], 0x20),
+    named("特殊键", Key::Enter, "Enter", &["RETURN"], 0x0D),
+    named("特殊键", Key::Tab, "Tab", &[], 0x09),
+    named("特殊键", Key::Escape, "Escape", &["ESC"], 0x1B),
+    named("特殊键", Key::Backspace, "Backspace", &[], 0x08),
+    named("特殊键", Key::Delete, "Delete", &["DEL"], 0x2E),
+    named("特殊键", Key::Insert, "Insert", &["INS"], 0x2D),
+    named("特殊键", Key::Home, "Home", &[], 0x24),
+    named("特殊键", Key::End, "End", &[], 0x23),
+    named("特殊键", Key::PageUp, "PageUp", &["PGUP"], 0x21),
+    named("特殊键", Key::PageDown, "PageDown", &["PGDN"], 0x22),
+    named("特殊键", Key::PrintScreen, "PrintScreen", &["PRTSC", "PRTSCN"], 0x2C),
+    named("特殊键", Key::Pause, "Pause", &["BREAK"], 0x13),
+    named("特殊键", Key::ScrollLock, "ScrollLock", &["SCRLK"], 0x91),
+
+    named("方向键", Key::Up, "Up", &[], 0x26),
+    named("方向键", Key::Down, "Down", &[], 0x28),
+    named("方向键", Key::Left, "Left", &[], 0x25),
+    named("方向键", Key::Right, "Right", &[], 0x27),
+
+    named("符号键", Key::Backquote, "`", &["~"], 0xC0),
+    named("符号键", Key::Minus, "-", &["_"], 0xBD),
+    named("符号键", Key::Equal, "=", &["+"], 0xBB),
+    named("符号键", Key::BracketLeft, "[", &["{"], 0xDB),
+    named("符号键", Key::BracketRight, "]", &["}"], 0xDD),
+    named("符号键", Key::Backslash, "\\", &["|"], 0xDC),
+    named("符号键", Key::Semicolon, ";", &[":"], 0xBA),
+    named("符号键", Key::Quote, "'", &["\""], 0xDE),
+    named("符号键", Key::Comma, ",", &["<"], 0xBC),
+    named("符号键", Key::Period, ".", &[">"], 0xBE),
+    named("符号键", Key::Slash, "/", &["?"], 0xBF),
+
+    named("媒体键", Key::VolumeMute, "VolumeMute", &["MUTE"], 0xAD),
+    named("媒体键", Key::VolumeDown, "VolumeDown", &[], 0xAE),
+    named("媒体键", Key::VolumeUp, "VolumeUp", &[], 0xAF),
+    named("媒体键", Key::MediaPlayPause, "MediaPlayPause", &["PLAYPAUSE"], 0xB3),
+    named("媒体键", Key::MediaStop, "MediaStop", &[], 0xB2),
+    named("媒体键", Key::MediaNext, "MediaNext", &["MEDIANEXTTRACK"], 0xB0),
+    named("媒体键", Key::MediaPrevious, "MediaPrevious", &["MEDIAPREV", "MEDIAPREVTRACK"], 0xB1),
+
+    named("浏览器键", Key::BrowserBack, "BrowserBack", &[], 0xA6),
+    named("浏览器键", Key::BrowserForward, "BrowserForward", &[], 0xA7),
+    named("浏览器键", Key::BrowserRefresh, "BrowserRefresh", &[], 0xA8),
+    named("浏览器键", Key::BrowserStop, "BrowserStop", &[], 0xA9),
+    named("浏览器键", Key::BrowserSearch, "BrowserSearch", &[], 0xAA),
+    named("浏览器键", Key::BrowserFavorites, "BrowserFavorites", &[], 0xAB),
+    named("浏览器键", Key::BrowserHome, "BrowserHome", &[], 0xAC),
+];
+
+impl Key {
+    /// 解析按键名称（不区分大小写）
+    pub fn from_name(name: &str) -> Option<Key> {
+        let upper = name.to_uppercase();
+
+        if let Some(named) = NAMED
+            .iter()
+            .find(|n| n.name.to_uppercase() == upper || n.aliases.contains(&upper.as_str()))
+        {
+            return Some(named.key);
+        }
+        if let Some(code) = upper.strip_prefix("VK:") {
+            return Self::parse_vk(code);
+        }
+        if let Some(code) = upper.strip_prefix("SC:") {
+            return Self::parse_scan(code);
+        }
+
+        let mut chars = upper.chars();
+        match (chars.next(), chars.next()) {
+            // 字母键 (A-Z)
+            (Some(c), None) if c.is_ascii_alphabetic() => Some(Key::Letter(c)),
+            // 数字键 (0-9)
+            (Some(c), None) if c.is_ascii_digit() => Some(Key::Digit(c)),
+            // 功能键 / 数字键盘
+            _ => Self::parse_numbered(&upper),
+        }
+    }
+
+    /// 解析带编号的按键 (F1-F24, Numpad0-9)
+    fn parse_numbered(upper: &str) -> Option<Key> {
+        // 只接受规范写法，"F01" 之类的不算
+        let number = |digits: &str| match digits.parse::<u8>() {
+            Ok(n) if n.to_string() == digits => Some(n),
+            _ => None,
+        };
+
+        if let Some(n) = upper.strip_prefix('F') {
+            return number(n).filter(|n| (1..=MAX_F).contains(n)).map(Key::F);
+        }
+
+        let n = upper
+            .strip_prefix("NUMPAD")
+            .or_else(|| upper.strip_prefix("NUM"))?;
+        number(n).filter(|n| *n <= 9).map(Key::Numpad)
+    }
+
+    /// 解析 `vk:` 后面的虚拟键码（十六进制 `0x7C` 或十进制 `124`），有名称的按键换成对应的名称
+    fn parse_vk(code: &str) -> Option<Key> {
+        let code = match code.strip_prefix("0X") {
+            Some(hex) => u8::from_str_radix(hex, 16).ok()?,
+            None => code.parse().ok()?,
+        };
+        if code == 0 || code == 0xFF {
+            return None;
+        }
+        let named = Self::all()
+            .into_iter()
+            .find(|key| key.vk_code() == code as u32);
+        Some(named.unwrap_or(Key::Vk(code)))
+    }
+
+    /// 解析 `sc:` 后面的扫描码：`0x01`-`0x7F`，扩展键加 `0xE0` 前缀
+    fn parse_scan(code: &str) -> Option<Key> {
+        let code = match code.strip_prefix("0X") {
+            Some(hex) => u16::from_str_radix(hex, 16).ok()?,
+            None => code.parse().ok()?,
+        };
+        let valid = matches!(code & 0xFF00, 0 | 0xE000) && (1..=0x7F).contains(&(code & 0xFF));
+        valid.then_some(Key::Scan(code))
+    }
+
+    /// 按键分组，`Key::all` 和 README 中的按键列表都由它生成
+    pub fn groups() -> Vec<(&'static str, Vec<Key>)> {
+        let mut groups = vec![
+            ("字母键", ('A'..='Z').map(Key::Letter).collect()),
+            ("数字键", ('0'..='9').map(Key::Digit).collect()),
+            ("功能键", (1..=MAX_F).map(Key::F).collect()),
+            ("数字键盘", (0..=9).map(Key::Numpad).collect::<Vec<_>>()),
+        ];
+        for named in NAMED {
+            match groups.iter_mut().find(|(title, _)| *title == named.group) {
+                Some((_, keys)) => keys.push(named.key),
+                None => groups.push((named.group, vec![named.key])),
+            }
+        }
+        groups
+    }
+
+    /// 所有有名称的按键
+    pub fn all() -> Vec<Key> {
+        Self::groups()
+            .into_iter()
+            .flat_map(|(_, keys)| keys)
+            .collect()
+    }
+
+    fn named(&self) -> Option<&'static Named> {
+        NAMED.iter().find(|n| n.key == *self)
+    }
+
+    /// 规范名称
+    pub fn name(&self) -> String {
+        match self {
+            Key::F(n) => format!("F{}", n),
+            Key::Letter(c) | Key::Digit(c) => c.to_string(),
+            Key::Numpad(n) => format!("Numpad{}", n),
+            Key::Vk(code) => format!("vk:0x{:02X}", code),
+            Key::Scan(code) => format!("sc:0x{:02X}", code),
+            key => key
+                .named()
+                .expect("按键表中有所有固定名称的按键")
+                .name
+                .into(),
+        }
+    }
+
+    /// 显示给用户的名称：扫描码按键为当前键盘布局上的名称，无法获取时同 [`Key::name`]
+    pub fn label(&self) -> String {
+        match self {
+            Key::Scan(code) => platform::scan_code_label(*code).unwrap_or_else(|| self.name()),
+            _ => self.name(),
+        }
+    }
+
+    /// Windows 虚拟键码；扫描码按键按当前键盘布局换算，无法换算时为 0
+    pub fn vk_code(&self) -> u32 {
+        match self {
+            Key::F(n) => 0x70 + (*n as u32 - 1),
+            Key::Letter(c) | Key::Digit(c) => *c as u32,
+            Key::Numpad(n) => 0x60 + *n as u32,
+            Key::Vk(code) => *code as u32,
+            Key::Scan(code) => platform::scan_code_vk(*code).unwrap_or(0),
+            key => key.named().expect("按键表中有所有固定名称的按键").vk as u32,
+        }
+    }
+}
+
+/// README 中的按键列表（Markdown），编号连续的按键写成范围
+pub fn markdown_list() -> String {
+    let mut text = String::new();
+    for (title, keys) in Key::groups() {
+        let names = ranges(&keys, code).join(", ");
+        text.push_str(&format!("- **{}**: {}\n", title, names));
+    }
+    text.push_str("- **虚拟键码**: 表中没有的按键可以直接写 Windows 虚拟键码，如 `vk:0x7C`\n");
+    text.push_str(
+        "- **扫描码**: 按物理位置指定，不受键盘布局影响，如 `sc:0x35`（美式键盘的 `/`），扩展键如 `sc:0xE035`\n",
+    );
+    text
+}
+
+/// 配置文件模板中的按键列表（TOML 注释），与 [`markdown_list`] 内容相同
+pub fn comment_list() -> String {
+    let mut text = String::new();
+    for (title, keys) in Key::groups() {
+        let names = ranges(&keys, |key| key.name()).join(" ");
+        text.push_str(&format!("#   {}: {}\n", title, names));
+    }
+    text.push_str("#   虚拟键码: vk:0x7C 等，扫描码（按物理位置）: sc:0x35 等\n");
+    text
+}
+
+/// 同一种带编号的按键，虚拟键码连续时合并为 `F1-F24` 这样的范围
+fn ranges(keys: &[Key], name: impl Fn(&Key) -> String) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut i = 0;
+    while i < keys.len() {
+        let mut j = i;
+        while j + 1 < keys.len()
+            && std::mem::discriminant(&keys[j]) == std::mem::discriminant(&keys[j + 1])
+            && keys[j].vk_code() + 1 == keys[j + 1].vk_code()
+        {
+            j += 1;
+        }
+        if j - i >= 2 {
+            parts.push(format!("{}-{}", name(&keys[i]), name(&keys[j])));
+        } else {
+            parts.extend(keys[i..=j].iter().map(&name));
+        }
+        i = j + 1;
+    }
+    parts
+}
+
+/// 符号键名写成代码格式
+fn code(key: &Key) -> String {
+    let name = key.name();
+    if name.chars().all(|c| c.is_ascii_alphanumeric()) {
+        name
+    } else if name.contains('`') {
+        format!("`` {} ``", name)
+    } else {
+        format!("`{}`", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_names_case_insensitively() {
+        assert_eq!(Key::from_name("space"), Some(Key::Space));
+        assert_eq!(Key::from_name("t"), Some(Key::Letter('T')));
+        assert_eq!(Key::from_name("7"), Some(Key::Digit('7')));
+        assert_eq!(Key::from_name("f9"), Some(Key::F(9)));
+        assert_eq!(Key::from_name("Num3"), Some(Key::Numpad(3)));
+        assert_eq!(Key::from_name("?"), Some(Key::Slash));
+        assert_eq!(Key::from_name("prtsc"), Some(Key::PrintScreen));
+        assert_eq!(Key::from_name("volumeup"), Some(Key::VolumeUp));
+    }
+
+    #[test]
+    fn rejects_unknown_names() {
+        assert_eq!(Key::from_name("F25"), None);
+        assert_eq!(Key::from_name("F0"), None);
+        assert_eq!(Key::from_name("Numpad10"), None);
+        assert_eq!(Key::from_name("Spcae"), None);
+        assert_eq!(Key::from_name("vk:0x00"), None);
+        assert_eq!(Key::from_name("vk:0x100"), None);
+        assert_eq!(Key::from_name("vk:"), None);
+        assert_eq!(Key::from_name(""), None);
+    }
+
+    #[test]
+    fn maps_to_win32_virtual_keys() {
+        assert_eq!(Key::Space.vk_code(), 0x20);
+        assert_eq!(Key::F(1).vk_code(), 0x70);
+        assert_eq!(Key::F(12).vk_code(), 0x7B);
+        assert_eq!(Key::Letter('A').vk_code(), 0x41);
+        assert_eq!(Key::Digit('0').vk_code(), 0x30);
+        assert_eq!(Key::Numpad(9).vk_code(), 0x69);
+        assert_eq!(Key::Slash.vk_code(), 0xBF);
+        assert_eq!(Key::F(13).vk_code(), 0x7C);
+        assert_eq!(Key::F(24).vk_code(), 0x87);
+        assert_eq!(Key::PrintScreen.vk_code(), 0x2C);
+        assert_eq!(Key::NumpadAdd.vk_code(), 0x6B);
+        assert_eq!(Key::MediaPlayPause.vk_code(), 0xB3);
+    }
+
+    #[test]
+    fn virtual_key_codes_are_unique() {
+        let mut codes: Vec<u32> = Key::all().iter().map(Key::vk_code).collect();
+        codes.sort_unstable();
+        codes.dedup();
+        assert_eq!(codes.len(), Key::all().len());
+    }
+
+    #[test]
+    fn raw_virtual_keys() {
+        assert_eq!(Key::from_name("vk:0x7C"), Some(Key::F(13)));
+        assert_eq!(Key::from_name("VK:65"), Some(Key::Letter('A')));
+        assert_eq!(Key::from_name("vk:0xE8"), Some(Key::Vk(0xE8)));
+        assert_eq!(Key::Vk(0xE8).name(), "vk:0xE8");
+        assert_eq!(Key::Vk(0xE8).vk_code(), 0xE8);
+    }
+
+    #[test]
+    fn scan_codes() {
+        assert_eq!(Key::from_name("sc:0x35"), Some(Key::Scan(0x35)));
+        assert_eq!(Key::from_name("SC:0xe035"), Some(Key::Scan(0xE035)));
+        assert_eq!(Key::from_name("sc:53"), Some(Key::Scan(0x35)));
+        assert_eq!(Key::Scan(0x35).name(), "sc:0x35");
+        assert_eq!(Key::Scan(0xE035).name(), "sc:0xE035");
+        for bad in ["sc:0x00", "sc:0x80", "sc:0x1035", "sc:0xE100", "sc:x"] {
+            assert_eq!(Key::from_name(bad), None, "{bad}");
+        }
+    }
+
+    #[test]
+    fn readme_key_list_is_generated() {
+        // UPDATE_README=1 cargo test 重新生成
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/README.md");
+        let readme = std::fs::read_to_string(path).unwrap();
+        let (start, end) = ("<!-- keys:start -->\n", "<!-- keys:end -->");
+        let from = readme.find(start).expect("README 中缺少按键列表的开始标记") + start.len();
+        let to = readme.find(end).expect("README 中缺少按键列表的结束标记");
+        let list = markdown_list();
+        if std::env::var_os("UPDATE_README").is_some() {
+            let updated = format!("{}{}{}", &readme[..from], list, &readme[to..]);
+            std::fs::write(path, updated).unwrap();
+        } else {
+            assert_eq!(
+                readme[from..to],
+                list,
+                "README 中的按键列表已过期，请运行 UPDATE_README=1 cargo test"
+            );
+        }
+    }
+
+    #[test]
+    fn all_keys_round_trip() {
+        for key in Key::all() {
+            assert_eq!(Key::from_name(&key.name()), Some(key));
+        }
+    }
+
+    #[test]
+    fn canonical_name_round_trips() {
+        for name in [
+            "F5",
+            "F20",
+            "A",
+            "3",
+            "Numpad4",
+            "PageUp",
+            "`",
+            "/",
+            "NumpadAdd",
+        ] {
+            let key = Key::from_name(name).unwrap();
+            assert_eq!(key.name(), name);
+            assert_eq!(Key::from_name(&key.name()), Some(key));
+        }
+    }
+}
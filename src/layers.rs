@@ -0,0 +1,544 @@
+//! 分层配置
+//!
+//! 最终配置由以下几层依次合并，后面的覆盖前面的：
+//! 1. 内置默认值
+//! 2. 全局配置（见 [`location::machine_config`]）
+//! 3. 用户配置文件
+//! 4. 管理员策略（见 [`Policy`]）：锁定的设置总是生效，用户配置和引入的文件中不能改成其他值
+//!
+//! 每个文件都可以用 `include = ["team.toml"]` 引入其他文件（相对于该文件所在目录），
+//! 被引入的文件排在引入它的文件之前，因此文件自己写的值优先。
+//!
+//! 合并规则：`hotkey` 整体替换；`[settings]` 按字段覆盖；
+//! `[[bindings]]` 依次追加，快捷键相同的由后面的替换。
+//!
+//! [`location::machine_config`]: crate::location::machine_config
+
+use crate::config::{Binding, Config};
+use crate::error::ConfigError;
+use crate::policy::Policy;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+/// 配置层的种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayerKind {
+    /// 内置默认值
+    Default,
+    /// 全局配置
+    Machine,
+    /// 通过 `include` 引入的文件
+    Include,
+    /// 用户配置
+    User,
+    /// 管理员策略
+    Policy,
+}
+
+/// 一层配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layer {
+    pub kind: LayerKind,
+    /// 内置默认值没有文件
+    pub path: Option<PathBuf>,
+}
+
+impl fmt::Display for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            LayerKind::Default => "默认值",
+            LayerKind::Machine => "全局配置",
+            LayerKind::Include => "包含文件",
+            LayerKind::User => "用户配置",
+            LayerKind::Policy => "管理员策略",
+        };
+        match &self.path {
+            Some(path) => write!(f, "{} {}", kind, path.display()),
+            None => f.write_str(kind),
+        }
+    }
+}
+
+/// 合并后的配置和每个值的来源
+#[derive(Debug, Clone)]
+pub struct Layered {
+    pub config: Config,
+    /// 按合并顺序排列，第一个是内置默认值
+    pub layers: Vec<Layer>,
+    /// 管理员策略
+    pub policy: Policy,
+    /// 字段（如 `settings.play_sound`）→ 来源层
+    origins: BTreeMap<String, usize>,
+    /// 每个 `[[bindings]]` 的来源层
+    binding_origins: Vec<usize>,
+}
+
+impl Layered {
+    /// 字段的来源，如 `"hotkey"`、`"settings.play_sound"`、`"bindings[0]"`
+    pub fn origin(&self, key: &str) -> &Layer {
+        let index = match key
+            .strip_prefix("bindings[")
+            .and_then(|rest| rest.strip_suffix(']'))
+        {
+            Some(i) => i
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| self.binding_origins.get(i).copied()),
+            // `[[chords]]` 等其他数组整体来自同一层
+            None => self
+                .origins
+                .get(key.split('[').next().unwrap_or(key))
+                .copied(),
+        };
+        &self.layers[index.unwrap_or(0)]
+    }
+
+    /// 所有生效的值：（字段，值，来源）
+    pub fn entries(&self) -> Vec<(String, String, &Layer)> {
+        let mut entries = Vec::new();
+        let mut push = |key: String, value: String| {
+            let layer = self.origin(&key);
+            entries.push((key, value, layer));
+        };
+
+        push("hotkey".into(), self.config.hotkey.to_toml().to_string());
+        if let Ok(Value::Table(settings)) = Value::try_from(&self.config.settings) {
+            for (name, value) in settings {
+                push(format!("settings.{}", name), value.to_string());
+            }
+        }
+        for (i, binding) in self.config.bindings.iter().enumerate() {
+            push(
+                format!("bindings[{}]", i),
+                format!(
+                    "{{ hotkey = {}, action = \"{}\" }}",
+                    binding.hotkey.to_toml(),
+                    binding.action.name()
+                ),
+            );
+        }
+        for (i, chord) in self.config.chords.iter().enumerate() {
+            let keys: Table = chord
+                .keys
+                .iter()
+                .map(|(key, action)| (key.clone(), action.name().into()))
+                .collect();
+            push(
+                format!("chords[{}]", i),
+                format!(
+                    "{{ leader = {}, timeout = {}, keys = {} }}",
+                    chord.leader.to_toml(),
+                    chord.timeout,
+                    Value::Table(keys)
+                ),
+            );
+        }
+        entries
+    }
+
+    /// 各文件已经检查过，这里只会发现来自默认值或全局配置的违规
+    fn check_policy(&self, user: &Path) -> Result<(), ConfigError> {
+        for (i, chord) in self.config.chords.iter().enumerate() {
+            let candidates = chord.leader.candidates().unwrap_or_default();
+            let hotkey = candidates.iter().find(|a| self.policy.forbids_hotkey(a));
+            let action = chord
+                .keys
+                .iter()
+                .map(|(_, action)| action)
+                .find(|a| self.policy.forbids_action(**a));
+            let forbidden = match (hotkey, action) {
+                (Some(accel), _) => format!("快捷键 {} 已被管理员禁止", accel),
+                (None, Some(action)) => format!("动作 {} 已被管理员禁止", action.name()),
+                (None, None) => continue,
+            };
+            let path = self.origin(&format!("chords[{}]", i)).path.as_deref();
+            return Err(ConfigError::Policy {
+                path: path.unwrap_or(user).to_path_buf(),
+                line: 1,
+                column: 1,
+                message: format!("{}，请在配置文件中换一个", forbidden),
+            });
+        }
+        for (i, binding) in self.config.all_bindings().iter().enumerate() {
+            let candidates = binding.hotkey.candidates().unwrap_or_default();
+            let forbidden = match candidates.iter().find(|a| self.policy.forbids_hotkey(a)) {
+                Some(accel) => format!("快捷键 {} 已被管理员禁止", accel),
+                _ if self.policy.forbids_action(binding.action) => {
+                    format!("动作 {} 已被管理员禁止", binding.action.name())
+                }
+                _ => continue,
+            };
+            let key = match i {
+                0 => "hotkey".to_string(),
+                i => format!("bindings[{}]", i - 1),
+            };
+            let path = self.origin(&key).path.as_deref().unwrap_or(user);
+            return Err(ConfigError::Policy {
+                path: path.to_path_buf(),
+                line: 1,
+                column: 1,
+                message: format!("{}，请在配置文件中换一个", forbidden),
+            });
+        }
+        Ok(())
+    }
+
+    /// 参与合并的文件，用于监视变化
+    pub fn files(&self) -> Vec<PathBuf> {
+        self.layers.iter().filter_map(|l| l.path.clone()).collect()
+    }
+}
+
+/// 按层加载配置，`machine` 不存在时跳过
+pub fn load(user: &Path, machine: Option<&Path>, policy: Policy) -> Result<Layered, ConfigError> {
+    let machine = machine.filter(|path| path.exists());
+    let mut loader = Loader {
+        layers: vec![Layer {
+            kind: LayerKind::Default,
+            path: None,
+        }],
+        merged: Table::new(),
+        origins: BTreeMap::new(),
+        bindings: Vec::new(),
+        stack: Vec::new(),
+        policy,
+    };
+
+    if let Some(path) = machine {
+        loader.file(path, LayerKind::Machine, false)?;
+    }
+    if !user.exists() {
+        create_user_file(user, machine)?;
+    }
+    loader.file(user, LayerKind::User, true)?;
+    loader.finish(user)
+}
+
+/// 第一次运行时创建用户配置；有全局配置时只写版本号，其余的值沿用全局配置
+fn create_user_file(path: &Path, machine: Option<&Path>) -> Result<(), ConfigError> {
+    let Some(machine) = machine else {
+        return Config::default().save_to(path);
+    };
+    let content = format!(
+        "# 窗口置顶工具用户配置\n# 没有写的设置使用全局配置 {} 中的值\nversion = {}\n",
+        machine.display(),
+        crate::migrate::CURRENT_VERSION
+    );
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        let _ = fs::create_dir_all(dir);
+    }
+    fs::write(path, content).map_err(|source| ConfigError::Write {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+struct Loader {
+    layers: Vec<Layer>,
+    merged: Table,
+    origins: BTreeMap<String, usize>,
+    bindings: Vec<(Value, usize)>,
+    /// 正在加载的文件，用于发现循环包含
+    stack: Vec<PathBuf>,
+    policy: Policy,
+}
+
+impl Loader {
+    /// 加载一个文件：先合并它引入的文件，再合并它自己
+    fn file(&mut self, path: &Path, kind: LayerKind, rewrite: bool) -> Result<(), ConfigError> {
+        let key = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if self.stack.contains(&key) {
+            return Err(ConfigError::IncludeCycle {
+                path: path.to_path_buf(),
+            });
+        }
+
+        let content = Config::read_file(path, rewrite)?;
+        // 逐个文件检查，错误信息指向出错的文件
+        Config::parse(&content, path)?;
+        // 全局配置由管理员维护，不受策略检查
+        if kind != LayerKind::Machine {
+            if let Some(violation) = self.policy.check(&content, path).into_iter().next() {
+                return Err(ConfigError::Policy {
+                    path: path.to_path_buf(),
+                    line: violation.line,
+                    column: violation.column,
+                    message: violation.message,
+                });
+            }
+        }
+        let mut table: Table = toml::from_str(&content).map_err(|e| ConfigError::Parse {
+            path: path.to_path_buf(),
+            line: 1,
+            column: 1,
+            message: e.message().to_string(),
+        })?;
+
+        self.stack.push(key);
+        if let Some(Value::Array(includes)) = table.remove("include") {
+            let dir = path.parent().unwrap_or(Path::new("."));
+            for include in includes.iter().filter_map(Value::as_str) {
+                self.file(&dir.join(include), LayerKind::Include, false)?;
+            }
+        }
+        self.stack.pop();
+
+        table.remove("version");
+        self.layers.push(Layer {
+            kind,
+            path: Some(path.to_path_buf()),
+        });
+        self.merge(table, self.layers.len() - 1);
+        Ok(())
+    }
+
+    fn merge(&mut self, table: Table, layer: usize) {
+        for (key, value) in table {
+            match (key.as_str(), value) {
+                ("settings", Value::Table(settings)) => {
+                    let merged = self
+                        .merged
+                        .entry("settings")
+                        .or_insert_with(|| Value::Table(Table::new()));
+                    if let Value::Table(merged) = merged {
+                        for (name, value) in settings {
+                            self.origins.insert(format!("settings.{}", name), layer);
+                            merged.insert(name, value);
+                        }
+                    }
+                }
+                ("bindings", Value::Array(bindings)) => {
+                    for binding in bindings {
+                        // 后面的层覆盖前面的层中相同的快捷键；同一层中的重复都保留，
+                        // 与配置检查一致，由快捷键管理器报告重复、只让第一个生效
+                        let accel = binding_accelerator(&binding);
+                        if accel.is_some() {
+                            self.bindings
+                                .retain(|(b, l)| *l == layer || binding_accelerator(b) != accel);
+                        }
+                        self.bindings.push((binding, layer));
+                    }
+                }
+                (_, value) => {
+                    self.origins.insert(key.clone(), layer);
+                    self.merged.insert(key, value);
+                }
+            }
+        }
+    }
+
+    fn finish(mut self, user: &Path) -> Result<Layered, ConfigError> {
+        let (bindings, binding_origins) = self.bindings.into_iter().unzip();
+        self.merged
+            .insert("bindings".into(), Value::Array(bindings));
+        let mut config: Config =
+            Value::Table(self.merged)
+                .try_into()
+                .map_err(|e: toml::de::Error| ConfigError::Parse {
+                    path: user.to_path_buf(),
+                    line: 1,
+                    column: 1,
+                    message: e.message().to_string(),
+                })?;
+
+        if !self.policy.is_empty() {
+            self.layers.push(Layer {
+                kind: LayerKind::Policy,
+                path: self.policy.path().map(Path::to_path_buf),
+            });
+            let layer = self.layers.len() - 1;
+            for name in self.policy.locked() {
+                self.origins.insert(format!("settings.{}", name), layer);
+            }
+            self.policy.apply(&mut config.settings);
+        }
+
+        let layered = Layered {
+            config,
+            layers: self.layers,
+            policy: self.policy,
+            origins: self.origins,
+            binding_origins,
+        };
+        layered.check_policy(user)?;
+        Ok(layered)
+    }
+}
+
+fn binding_accelerator(binding: &Value) -> Option<crate::accelerator::Accelerator> {
+    let binding: Binding = binding.clone().try_into().ok()?;
+    binding.hotkey.accelerator().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::Action;
+
+    /// 在临时目录中写入文件，测试结束时删除
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "window-topmost-{}-{}",
+                name,
+                std::process::id()
+            ));
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn write(&self, name: &str, content: &str) -> PathBuf {
+            let path = self.0.join(name);
+            fs::write(&path, content).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn later_layers_take_precedence() {
+        let dir = TempDir::new("layers");
+        let machine = dir.write(
+            "machine.toml",
+            "version = 2\nhotkey = \"Ctrl+Alt+T\"\n\n[settings]\nplay_sound = false\nshow_notification = false\n",
+        );
+        dir.write(
+            "team.toml",
+            "[[bindings]]\nhotkey = \"F9\"\naction = \"unpin_all\"\n\n[[bindings]]\nhotkey = \"F10\"\naction = \"reload\"\n",
+        );
+        let user = dir.write(
+            "user.toml",
+            "version = 2\ninclude = [\"team.toml\"]\n\n[settings]\nplay_sound = true\n\n[[bindings]]\nhotkey = \"F9\"\naction = \"unpin\"\n",
+        );
+
+        let layered = load(&user, Some(&machine), Policy::default()).unwrap();
+        let config = &layered.config;
+        assert_eq!(config.hotkey.display(), "Ctrl+Alt+T");
+        assert!(config.settings.play_sound);
+        assert!(!config.settings.show_notification);
+        let bindings: Vec<_> = config
+            .bindings
+            .iter()
+            .map(|b| (b.hotkey.display(), b.action))
+            .collect();
+        assert_eq!(
+            bindings,
+            vec![
+                ("F10".to_string(), Action::Reload),
+                ("F9".to_string(), Action::Unpin),
+            ]
+        );
+
+        let kinds: Vec<_> = layered.layers.iter().map(|l| l.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                LayerKind::Default,
+                LayerKind::Machine,
+                LayerKind::Include,
+                LayerKind::User
+            ]
+        );
+        assert_eq!(layered.origin("hotkey").kind, LayerKind::Machine);
+        assert_eq!(layered.origin("settings.play_sound").kind, LayerKind::User);
+        assert_eq!(
+            layered.origin("settings.show_notification").kind,
+            LayerKind::Machine
+        );
+        assert_eq!(layered.origin("bindings[0]").kind, LayerKind::Include);
+        assert_eq!(layered.origin("bindings[1]").kind, LayerKind::User);
+    }
+
+    #[test]
+    fn duplicates_within_one_file_are_kept_in_order() {
+        let dir = TempDir::new("duplicates");
+        let user = dir.write(
+            "user.toml",
+            "version = 2\n\n[[bindings]]\nhotkey = \"F9\"\naction = \"unpin_all\"\n\n[[bindings]]\nhotkey = \"F9\"\naction = \"exit\"\n",
+        );
+
+        let layered = load(&user, None, Policy::default()).unwrap();
+        let actions: Vec<_> = layered.config.bindings.iter().map(|b| b.action).collect();
+        assert_eq!(actions, vec![Action::UnpinAll, Action::Exit]);
+    }
+
+    #[test]
+    fn missing_values_come_from_defaults() {
+        let dir = TempDir::new("defaults");
+        let user = dir.write("config.toml", "version = 2\n");
+        let layered = load(&user, None, Policy::default()).unwrap();
+        assert_eq!(layered.config.hotkey.display(), "Ctrl+Space");
+        assert_eq!(layered.origin("hotkey").kind, LayerKind::Default);
+
+        let entries = layered.entries();
+        assert_eq!(entries[0].0, "hotkey");
+        assert_eq!(entries[0].1, "\"Ctrl+Space\"");
+        assert_eq!(entries[0].2.to_string(), "默认值");
+    }
+
+    #[test]
+    fn include_errors_point_at_the_included_file() {
+        let dir = TempDir::new("include-errors");
+        let team = dir.write("team.toml", "hotkey = \"Ctrl+Spcae\"\n");
+        let user = dir.write("config.toml", "include = [\"team.toml\"]\n");
+        match load(&user, None, Policy::default()) {
+            Err(ConfigError::Parse { path, .. }) => assert_eq!(path, team),
+            other => panic!("{:?}", other),
+        }
+
+        dir.write("team.toml", "include = [\"config.toml\"]\n");
+        assert!(matches!(
+            load(&user, None, Policy::default()),
+            Err(ConfigError::IncludeCycle { .. })
+        ));
+    }
+
+    #[test]
+    fn policy_locks_settings_above_every_layer() {
+        let dir = TempDir::new("policy");
+        let policy = Policy::parse(
+            "[settings]\nplay_sound = false\n\n[forbid]\nactions = [\"exit\"]\n",
+            Path::new("policy.toml"),
+        )
+        .unwrap();
+        let machine = dir.write("machine.toml", "[settings]\nplay_sound = true\n");
+        let user = dir.write("config.toml", "version = 2\n");
+
+        let layered = load(&user, Some(&machine), policy.clone()).unwrap();
+        assert!(!layered.config.settings.play_sound);
+        let origin = layered.origin("settings.play_sound");
+        assert_eq!(origin.kind, LayerKind::Policy);
+        assert_eq!(origin.to_string(), "管理员策略 policy.toml");
+
+        dir.write(
+            "config.toml",
+            "version = 2\n\n[[bindings]]\nhotkey = \"F9\"\naction = \"exit\"\n",
+        );
+        match load(&user, Some(&machine), policy) {
+            Err(ConfigError::Policy { path, line, .. }) => {
+                assert_eq!((path, line), (user, 5));
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn new_user_file_defers_to_machine_config() {
+        let dir = TempDir::new("new-user");
+        let machine = dir.write("machine.toml", "hotkey = \"F8\"\n");
+        let user = dir.0.join("user").join("config.toml");
+        let layered = load(&user, Some(&machine), Policy::default()).unwrap();
+        assert_eq!(layered.config.hotkey.display(), "F8");
+        assert!(fs::read_to_string(&user).unwrap().contains("version = 2"));
+    }
+}
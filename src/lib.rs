@@ -0,0 +1,51 @@
+//! 窗口置顶工具库
+//!
+//! 提供配置模型、按键解析以及跨平台的窗口置顶操作，托盘程序只是它的一个使用者。
+//!
+//! ```no_run
+//! use window_topmost::{Config, Pinner};
+//!
+//! let config = Config::load()?;
+//! let mut pinner = Pinner::native()?;
+//! let outcome = pinner.toggle();
+//! if let Some((title, message)) = outcome.feedback(&config.settings).notification {
+//!     println!("[{}] {}", title, message);
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+pub mod accelerator;
+pub mod action;
+pub mod chord;
+pub mod config;
+pub mod diagnostics;
+mod edit;
+pub mod error;
+pub mod hotkeys;
+pub mod keypath;
+pub mod keys;
+pub mod layers;
+pub mod location;
+mod migrate;
+pub mod platform;
+pub mod policy;
+pub mod schema;
+pub mod state;
+pub mod watch;
+pub mod window;
+
+mod pinner;
+
+pub use accelerator::Accelerator;
+pub use action::{Action, ActionHost, Dispatcher, Source};
+pub use config::{Binding, ChordConfig, Config, HotkeyConfig, Settings};
+pub use error::{ConfigError, HotkeyError, StartupError, TrayError};
+pub use hotkeys::{HotkeyManager, Registrar};
+pub use keys::Key;
+pub use layers::{Layer, LayerKind, Layered};
+pub use location::ConfigLocation;
+pub use pinner::Pinner;
+pub use platform::{Process, WindowBackend, WindowId};
+pub use policy::Policy;
+pub use state::{AppState, PinnedWindow};
+pub use window::{Feedback, ToggleOutcome};
@@ -0,0 +1,279 @@
+//! 配置文件位置
+//!
+//! 按以下顺序确定配置文件：
+//! 1. 命令行参数 `--config <路径>`
+//! 2. 环境变量 `WINDOW_TOPMOST_CONFIG`
+//! 3. 便携模式：程序目录下有 `portable.txt`
+//! 4. 用户配置目录：`%APPDATA%\window-topmost\config.toml`，
+//!    或 `$XDG_CONFIG_HOME/window-topmost/config.toml`（默认 `~/.config`）
+//!
+//! 旧版本把配置文件放在程序目录下；使用用户配置目录且其中还没有配置文件时，把它复制过去。
+
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// 指定配置文件的环境变量
+pub const ENV_VAR: &str = "WINDOW_TOPMOST_CONFIG";
+
+/// 便携模式标记文件，放在程序目录下
+pub const PORTABLE_MARKER: &str = "portable.txt";
+
+/// 配置文件名
+const FILE_NAME: &str = "config.toml";
+
+/// 用户配置目录下的子目录
+const APP_DIR: &str = "window-topmost";
+
+/// 配置文件位置的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathSource {
+    /// 命令行参数 `--config`
+    CommandLine,
+    /// 环境变量
+    Environment,
+    /// 程序目录（便携模式）
+    Portable,
+    /// 用户配置目录
+    User,
+}
+
+impl fmt::Display for PathSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PathSource::CommandLine => "命令行参数 --config",
+            PathSource::Environment => "环境变量 WINDOW_TOPMOST_CONFIG",
+            PathSource::Portable => "便携模式",
+            PathSource::User => "用户配置目录",
+        })
+    }
+}
+
+/// 配置文件位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigLocation {
+    pub path: PathBuf,
+    pub source: PathSource,
+}
+
+/// 运行环境，测试时可以替换
+struct Environment {
+    var: Option<OsString>,
+    exe_dir: PathBuf,
+    user_dir: Option<PathBuf>,
+}
+
+impl Environment {
+    fn current() -> Self {
+        let exe = std::env::current_exe().unwrap_or_default();
+        Self {
+            var: std::env::var_os(ENV_VAR),
+            exe_dir: exe.parent().unwrap_or(Path::new(".")).to_path_buf(),
+            user_dir: user_config_dir(),
+        }
+    }
+}
+
+/// 系统的用户配置目录
+fn user_config_dir() -> Option<PathBuf> {
+    let dir = |name: &str| {
+        std::env::var_os(name)
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+    };
+    if cfg!(windows) {
+        dir("APPDATA")
+    } else {
+        dir("XDG_CONFIG_HOME").or_else(|| dir("HOME").map(|home| home.join(".config")))
+    }
+}
+
+impl ConfigLocation {
+    /// 按优先级确定配置文件位置，`flag` 为命令行参数 `--config` 的值
+    pub fn resolve(flag: Option<&Path>) -> Self {
+        let env = Environment::current();
+        let location = Self::resolve_in(flag, &env, &|path| path.exists());
+        if location.source == PathSource::User {
+            // 复制失败时照常使用用户配置目录，加载时创建默认配置
+            let _ = adopt_legacy(&env.exe_dir.join(FILE_NAME), &location.path);
+        }
+        location
+    }
+
+    fn resolve_in(flag: Option<&Path>, env: &Environment, exists: &dyn Fn(&Path) -> bool) -> Self {
+        let at = |path: PathBuf, source| Self { path, source };
+
+        if let Some(path) = flag {
+            return at(path.to_path_buf(), PathSource::CommandLine);
+        }
+        if let Some(path) = env.var.as_ref().filter(|v| !v.is_empty()) {
+            return at(PathBuf::from(path), PathSource::Environment);
+        }
+        let portable = env.exe_dir.join(FILE_NAME);
+        if exists(&env.exe_dir.join(PORTABLE_MARKER)) {
+            return at(portable, PathSource::Portable);
+        }
+        match &env.user_dir {
+            Some(dir) => at(dir.join(APP_DIR).join(FILE_NAME), PathSource::User),
+            // 找不到用户目录时只能用程序目录
+            None => at(portable, PathSource::Portable),
+        }
+    }
+}
+
+/// 把旧版本放在程序目录下的配置文件复制到 `target`，`target` 已经存在时不复制
+///
+/// 程序目录可能是只读的，原文件保留不动。返回是否复制了。
+fn adopt_legacy(legacy: &Path, target: &Path) -> io::Result<bool> {
+    if target.exists() || !legacy.is_file() {
+        return Ok(false);
+    }
+    if let Some(dir) = target.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::copy(legacy, target)?;
+    Ok(true)
+}
+
+/// `路径（来源）`
+impl fmt::Display for ConfigLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}（{}）", self.path.display(), self.source)
+    }
+}
+
+static LOCATION: OnceLock<ConfigLocation> = OnceLock::new();
+
+/// 启动时用命令行参数确定配置文件位置，只有第一次调用有效
+pub fn init(flag: Option<&Path>) -> &'static ConfigLocation {
+    LOCATION.get_or_init(|| ConfigLocation::resolve(flag))
+}
+
+/// 当前使用的配置文件位置；没有调用过 [`init`] 时按没有命令行参数处理
+pub fn current() -> &'static ConfigLocation {
+    init(None)
+}
+
+/// 全局配置目录：`%ProgramData%\window-topmost` 或 `/etc/window-topmost`
+fn machine_dir() -> Option<PathBuf> {
+    let dir = if cfg!(windows) {
+        PathBuf::from(std::env::var_os("ProgramData").filter(|v| !v.is_empty())?)
+    } else {
+        PathBuf::from("/etc")
+    };
+    Some(dir.join(APP_DIR))
+}
+
+/// 全局配置文件，位于全局配置目录下的 `config.toml`
+pub fn machine_config() -> Option<PathBuf> {
+    Some(machine_dir()?.join(FILE_NAME))
+}
+
+/// 管理员策略文件，位于全局配置目录下的 `policy.toml`
+pub fn policy_file() -> Option<PathBuf> {
+    Some(machine_dir()?.join("policy.toml"))
+}
+
+/// 缩短过长的路径，保留末尾（文件名所在的部分），`max` 按字符计
+pub fn abbreviate(path: &Path, max: usize) -> String {
+    let text = path.display().to_string();
+    let count = text.chars().count();
+    if count <= max {
+        return text;
+    }
+    let tail: String = text.chars().skip(count - max.saturating_sub(1)).collect();
+    format!("…{}", tail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(var: Option<&str>, user_dir: Option<&str>) -> Environment {
+        Environment {
+            var: var.map(OsString::from),
+            exe_dir: PathBuf::from("/opt/app"),
+            user_dir: user_dir.map(PathBuf::from),
+        }
+    }
+
+    fn resolve(flag: Option<&str>, env: &Environment, existing: &[&str]) -> ConfigLocation {
+        let exists = |path: &Path| existing.iter().any(|e| Path::new(e) == path);
+        ConfigLocation::resolve_in(flag.map(Path::new), env, &exists)
+    }
+
+    #[test]
+    fn follows_resolution_order() {
+        let full = env(Some("/etc/topmost.toml"), Some("/home/me/.config"));
+        let marker = ["/opt/app/portable.txt"];
+
+        let location = resolve(Some("my.toml"), &full, &marker);
+        assert_eq!(location.path, Path::new("my.toml"));
+        assert_eq!(location.source, PathSource::CommandLine);
+
+        let location = resolve(None, &full, &marker);
+        assert_eq!(location.path, Path::new("/etc/topmost.toml"));
+        assert_eq!(location.source, PathSource::Environment);
+
+        let no_var = env(None, Some("/home/me/.config"));
+        let location = resolve(None, &no_var, &marker);
+        assert_eq!(location.path, Path::new("/opt/app/config.toml"));
+        assert_eq!(location.source, PathSource::Portable);
+
+        let location = resolve(None, &no_var, &[]);
+        assert_eq!(
+            location.path,
+            Path::new("/home/me/.config/window-topmost/config.toml")
+        );
+        assert_eq!(location.source, PathSource::User);
+    }
+
+    #[test]
+    fn config_next_to_exe_alone_is_not_portable() {
+        let env = env(Some(""), Some("/home/me/.config"));
+        let location = resolve(None, &env, &["/opt/app/config.toml"]);
+        assert_eq!(location.source, PathSource::User);
+
+        let location = resolve(
+            None,
+            &Environment {
+                user_dir: None,
+                ..env
+            },
+            &[],
+        );
+        assert_eq!(location.path, Path::new("/opt/app/config.toml"));
+    }
+
+    #[test]
+    fn legacy_config_is_copied_to_user_dir() {
+        let dir =
+            std::env::temp_dir().join(format!("window-topmost-legacy-{}", std::process::id()));
+        let legacy = dir.join("app").join(FILE_NAME);
+        let target = dir.join("user").join(APP_DIR).join(FILE_NAME);
+        fs::create_dir_all(legacy.parent().unwrap()).unwrap();
+        fs::write(&legacy, "hotkey = \"F9\"\n").unwrap();
+
+        assert!(adopt_legacy(&legacy, &target).unwrap());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "hotkey = \"F9\"\n");
+        assert!(legacy.exists());
+
+        // 用户配置目录已经有配置文件时不覆盖
+        fs::write(&legacy, "hotkey = \"F8\"\n").unwrap();
+        assert!(!adopt_legacy(&legacy, &target).unwrap());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "hotkey = \"F9\"\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn abbreviates_long_paths_from_the_start() {
+        let path = Path::new("/a/very/long/directory/config.toml");
+        assert_eq!(abbreviate(path, 100), "/a/very/long/directory/config.toml");
+        assert_eq!(abbreviate(path, 16), "…ory/config.toml");
+        assert_eq!(abbreviate(path, 16).chars().count(), 16);
+    }
+}
@@ -5,35 +5,87 @@
 
 #![windows_subsystem = "windows"] // 隐藏控制台窗口
 
-mod config;
+#[cfg(windows)]
+mod app;
+mod cli;
+#[cfg(windows)]
+mod hint;
+#[cfg(windows)]
 mod hotkey;
+#[cfg(windows)]
+mod report;
+#[cfg(windows)]
 mod tray;
-mod window;
 
-use anyhow::Result;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use windows::Win32::UI::WindowsAndMessaging::{
-    DispatchMessageW, GetMessageW, TranslateMessage, MSG, WM_HOTKEY, WM_QUIT,
-};
+use window_topmost::Config;
 
 /// 程序入口
-fn main() -> Result<()> {
-    // 加载配置
-    let config = config::Config::load()?;
+///
+/// 启动失败时显示原因；配置有误时可以打开出错的文件，或重置用户配置后重试。
+#[cfg(windows)]
+fn main() {
+    use report::ConfigChoice;
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = cli::run(&args) {
+        std::process::exit(code);
+    }
+
+    loop {
+        let err = match run() {
+            Ok(()) => return,
+            Err(err) => err,
+        };
+
+        match report::report(&err) {
+            ConfigChoice::Open(path) => {
+                // 等待编辑器关闭后重新启动
+                let _ = std::process::Command::new("notepad.exe").arg(path).status();
+            }
+            ConfigChoice::Reset => {
+                if let Err(err) = Config::default().overwrite() {
+                    report::report(&err.into());
+                    std::process::exit(1);
+                }
+            }
+            ConfigChoice::Exit => std::process::exit(1),
+        }
+    }
+}
+
+/// 启动并运行托盘程序
+#[cfg(windows)]
+fn run() -> Result<(), window_topmost::StartupError> {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use windows::Win32::UI::WindowsAndMessaging::{
+        DispatchMessageW, GetMessageW, TranslateMessage, MSG, WM_QUIT,
+    };
+
+    // 加载配置（默认值、全局配置、用户配置合并）
+    let layered = Config::load_layered()?;
+    let config = layered.config.clone();
 
     // 运行标志
     let running = Arc::new(AtomicBool::new(true));
-    let running_clone = running.clone();
 
-    // 注册全局快捷键
-    let hotkey_id = hotkey::register_hotkey(&config.hotkey)?;
+    // 创建系统托盘，程序状态（含全局快捷键）由托盘窗口持有
+    let mut tray = tray::create_tray(&config)?;
+    let (app, notes) = app::App::new(
+        config.clone(),
+        layered.policy.clone(),
+        running.clone(),
+        tray.hwnd(),
+    );
+    tray.attach(app);
+    let notifier = tray.notifier();
 
-    // 创建系统托盘
-    let _tray = tray::create_tray(running_clone, config.clone())?;
+    // 配置文件变化时自动重新加载
+    app::spawn_config_watcher(notifier, layered.files());
 
     // 显示启动提示
-    tray::show_notification("窗口置顶工具", &format!("程序已启动！\n快捷键: {}", config.hotkey.display()));
+    let message = format!("程序已启动！\n快捷键: {}{}", config.hotkey.display(), notes);
+    notifier.notify("窗口置顶工具", &message);
 
     // 消息循环
     unsafe {
@@ -46,23 +98,34 @@ fn main() -> Result<()> {
                 break;
             }
 
-            match msg.message {
-                WM_HOTKEY => {
-                    if msg.wParam.0 as i32 == hotkey_id {
-                        window::toggle_topmost(&config);
-                    }
-                }
-                WM_QUIT => break,
-                _ => {
-                    let _ = TranslateMessage(&msg);
-                    DispatchMessageW(&msg);
-                }
+            if msg.message == WM_QUIT {
+                break;
             }
+            let _ = TranslateMessage(&msg);
+            DispatchMessageW(&msg);
         }
+    }
 
-        // 注销快捷键
-        hotkey::unregister_hotkey(hotkey_id);
+    Ok(())
+}
+
+/// 程序入口（Linux 桌面）
+///
+/// 没有托盘和全局快捷键，每次运行切换一次前台窗口的置顶状态，
+/// 可绑定到桌面环境的快捷键上使用。
+#[cfg(not(windows))]
+fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = cli::run(&args) {
+        std::process::exit(code);
     }
 
+    let config = Config::load()?;
+    let mut pinner = window_topmost::Pinner::native()?;
+
+    let outcome = pinner.toggle();
+    if let Some((title, message)) = outcome.feedback(&config.settings).notification {
+        println!("[{}] {}", title, message);
+    }
     Ok(())
 }
@@ -0,0 +1,273 @@
+//! 配置文件版本升级
+//!
+//! 配置文件顶层的 `version` 记录格式版本，没有写的是最早的版本 1。
+//! 加载时按 [`MIGRATIONS`] 逐版升级到 [`CURRENT_VERSION`]，注释和未知字段保持不变。
+
+use crate::accelerator::Accelerator;
+use crate::edit;
+use crate::error::ConfigError;
+use crate::keys::Key;
+use std::path::Path;
+use toml_edit::{Decor, DocumentMut, Item, Table, TableLike, Value};
+
+/// 当前的配置文件版本
+pub const CURRENT_VERSION: i64 = 2;
+
+/// 没有 `version` 字段时的版本
+const INITIAL_VERSION: i64 = 1;
+
+/// 写在 `version` 前面的说明
+const VERSION_COMMENT: &str = "# 配置文件版本，由程序维护，请勿修改\n";
+
+/// 一次升级：从 `from` 升到 `from + 1`
+struct Migration {
+    from: i64,
+    apply: fn(&mut DocumentMut),
+}
+
+/// 所有升级步骤，按版本排列
+const MIGRATIONS: [Migration; 1] = [Migration {
+    from: 1,
+    apply: hotkey_strings,
+}];
+
+/// 升级结果
+#[derive(Debug)]
+pub struct Upgrade {
+    /// 原来的版本
+    pub from: i64,
+    /// 升级后的配置内容
+    pub content: String,
+}
+
+/// 把配置内容升级到当前版本，已经是当前版本时返回 `None`
+///
+/// 语法错误和 `version` 类型错误不在这里处理，留给配置检查报告。
+pub fn upgrade(content: &str, path: &Path) -> Result<Option<Upgrade>, ConfigError> {
+    let Ok(mut doc) = content.parse::<DocumentMut>() else {
+        return Ok(None);
+    };
+    let from = match doc.get("version") {
+        None => INITIAL_VERSION,
+        Some(item) => match item.as_integer() {
+            Some(version) => version,
+            None => return Ok(None),
+        },
+    };
+    if from > CURRENT_VERSION {
+        return Err(ConfigError::Version {
+            path: path.to_path_buf(),
+            found: from,
+            supported: CURRENT_VERSION,
+        });
+    }
+    if from == CURRENT_VERSION {
+        return Ok(None);
+    }
+
+    for migration in MIGRATIONS.iter().filter(|m| m.from >= from) {
+        (migration.apply)(&mut doc);
+    }
+    set_version(&mut doc, CURRENT_VERSION);
+    Ok(Some(Upgrade {
+        from,
+        content: doc.to_string(),
+    }))
+}
+
+/// 写入版本号；新加的 `version` 放在文件开头的注释之后
+fn set_version(doc: &mut DocumentMut, version: i64) {
+    let root = doc.as_table_mut();
+    if root.contains_key("version") {
+        edit::replace(root, "version", version.into());
+        return;
+    }
+
+    // 空行之前是文件头，留在最前面；之后的注释属于原来的第一项
+    let head = with_first_decor(root, |decor| {
+        let prefix = decor
+            .prefix()
+            .and_then(|p| p.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let (head, rest) = prefix.split_at(prefix.rfind("\n\n").map_or(0, |i| i + 2));
+        decor.set_prefix(format!("\n{}", rest));
+        head.to_string()
+    });
+    let version_prefix = format!("{}{}", head.unwrap_or_default(), VERSION_COMMENT);
+
+    root.insert("version", Item::Value(version.into()));
+    if let Some(mut key) = root.key_mut("version") {
+        key.leaf_decor_mut().set_prefix(version_prefix);
+    }
+    root.sort_values_by(|a, _, b, _| (a.get() != "version").cmp(&(b.get() != "version")));
+}
+
+/// 修改文件中第一项的前缀注释：先是顶层字段，然后按出现顺序是各个表
+fn with_first_decor<R>(root: &mut Table, f: impl FnOnce(&mut Decor) -> R) -> Option<R> {
+    let first_value = root
+        .iter()
+        .find(|(_, item)| item.is_value())
+        .map(|(key, _)| key.to_string());
+    if let Some(name) = first_value {
+        return root.key_mut(&name).map(|mut key| f(key.leaf_decor_mut()));
+    }
+
+    root.iter_mut()
+        .filter_map(|(_, item)| match item {
+            Item::Table(table) => Some(table),
+            Item::ArrayOfTables(array) => array.get_mut(0),
+            _ => None,
+        })
+        .min_by_key(|table| table.position().unwrap_or(usize::MAX))
+        .map(|table| f(table.decor_mut()))
+}
+
+/// 1 → 2：表格写法的快捷键改为字符串，如 `hotkey = "Ctrl+Alt+T"`
+fn hotkey_strings(doc: &mut DocumentMut) {
+    let root = doc.as_table_mut();
+    hotkey_string(root, "hotkey");
+
+    match root.get_mut("bindings") {
+        Some(Item::ArrayOfTables(array)) => {
+            for table in array.iter_mut() {
+                hotkey_string(table, "hotkey");
+            }
+        }
+        Some(Item::Value(Value::Array(array))) => {
+            for table in array.iter_mut().filter_map(Value::as_inline_table_mut) {
+                hotkey_string(table, "hotkey");
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 把表格写法的快捷键换成字符串，无法识别的保持原样，由配置检查报告
+fn hotkey_string(parent: &mut dyn TableLike, key: &str) {
+    let Some(item) = parent.get(key) else {
+        return;
+    };
+    let Some(accel) = item.as_table_like().and_then(table_accelerator) else {
+        return;
+    };
+    let text = Value::from(accel.to_string());
+
+    // [hotkey] 表：表头前的注释移到新字段前面，表内的注释不再适用
+    let header = item.as_table().map(|table| {
+        table
+            .decor()
+            .prefix()
+            .and_then(|p| p.as_str())
+            .unwrap_or_default()
+            .to_string()
+    });
+    match header {
+        Some(prefix) => {
+            parent.insert(key, Item::Value(text));
+            if let Some(mut key) = parent.key_mut(key) {
+                key.leaf_decor_mut().set_prefix(prefix);
+            }
+        }
+        None => edit::replace(parent, key, text),
+    }
+}
+
+/// 只含布尔修饰键和 `key` 的快捷键表
+fn table_accelerator(table: &dyn TableLike) -> Option<Accelerator> {
+    let mut accel = Accelerator::new(Key::from_name(table.get("key")?.as_str()?)?);
+    for (name, item) in table.iter() {
+        let flag = match name {
+            "ctrl" => &mut accel.ctrl,
+            "alt" => &mut accel.alt,
+            "shift" => &mut accel.shift,
+            "win" => &mut accel.win,
+            "key" => continue,
+            _ => return None,
+        };
+        *flag = item.as_bool()?;
+    }
+    Some(accel)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::Action;
+    use crate::config::Config;
+
+    /// 每个升级步骤的（升级前，升级后）样例
+    const FIXTURES: [(&str, &str); 1] = [(
+        include_str!("../tests/fixtures/migrate/v1.toml"),
+        include_str!("../tests/fixtures/migrate/v2.toml"),
+    )];
+
+    fn summary(config: &Config) -> Vec<(String, Action)> {
+        config
+            .all_bindings()
+            .iter()
+            .map(|b| (b.hotkey.display(), b.action))
+            .collect()
+    }
+
+    fn path() -> &'static Path {
+        Path::new("config.toml")
+    }
+
+    #[test]
+    fn every_migration_has_a_fixture() {
+        assert_eq!(MIGRATIONS.len(), FIXTURES.len());
+        for (i, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.from, INITIAL_VERSION + i as i64);
+        }
+        assert_eq!(INITIAL_VERSION + MIGRATIONS.len() as i64, CURRENT_VERSION);
+    }
+
+    #[test]
+    fn migrations_match_fixtures() {
+        for (i, (before, after)) in FIXTURES.iter().enumerate() {
+            let mut doc: DocumentMut = before.parse().unwrap();
+            (MIGRATIONS[i].apply)(&mut doc);
+            let to = MIGRATIONS[i].from + 1;
+            set_version(&mut doc, to);
+            let migrated = doc.to_string();
+            assert_eq!(&migrated, after, "{} → {}", MIGRATIONS[i].from, to);
+
+            // 升级前后的配置内容相同
+            let old = Config::parse(before, path()).unwrap();
+            let new = Config::parse(&migrated, path()).unwrap();
+            assert_eq!(summary(&old), summary(&new));
+        }
+    }
+
+    #[test]
+    fn current_version_is_left_alone() {
+        let current = FIXTURES[FIXTURES.len() - 1].1;
+        assert!(upgrade(current, path()).unwrap().is_none());
+
+        let upgraded = upgrade(FIXTURES[0].0, path()).unwrap().unwrap();
+        assert_eq!(upgraded.from, INITIAL_VERSION);
+        assert_eq!(upgraded.content, current);
+    }
+
+    #[test]
+    fn unrecognised_hotkey_tables_are_kept() {
+        let content = "[hotkey]\nctrl = true\nkey = \"Spcae\"\n";
+        let upgraded = upgrade(content, path()).unwrap().unwrap();
+        assert_eq!(
+            upgraded.content,
+            format!(
+                "{}version = 2\n\n[hotkey]\nctrl = true\nkey = \"Spcae\"\n",
+                VERSION_COMMENT
+            )
+        );
+    }
+
+    #[test]
+    fn newer_versions_are_rejected() {
+        match upgrade("version = 99\n", path()) {
+            Err(ConfigError::Version { found, .. }) => assert_eq!(found, 99),
+            other => panic!("{:?}", other),
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! 置顶操作接口
+
+use crate::platform::{WindowBackend, WindowId};
+use crate::window::{self, ToggleOutcome};
+use anyhow::Result;
+
+/// 窗口置顶器
+///
+/// 对任意 [`WindowBackend`] 提供置顶、取消置顶、切换和查询操作。
+///
+/// ```
+/// use window_topmost::platform::sim::SimDesktop;
+/// use window_topmost::Pinner;
+///
+/// let mut desktop = SimDesktop::new();
+/// let notes = desktop.open("笔记");
+///
+/// let mut pinner = Pinner::new(desktop);
+/// pinner.pin(notes).unwrap();
+/// assert!(pinner.is_pinned(notes));
+/// assert_eq!(pinner.list_pinned(), vec![notes]);
+/// ```
+pub struct Pinner<B: WindowBackend> {
+    backend: B,
+}
+
+impl<B: WindowBackend> Pinner<B> {
+    /// 使用指定的窗口后端
+    pub fn new(backend: B) -> Self {
+        Self { backend }
+    }
+
+    /// 窗口后端
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// 窗口后端（可变）
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
+    /// 取回窗口后端
+    pub fn into_backend(self) -> B {
+        self.backend
+    }
+
+    /// 置顶窗口
+    pub fn pin(&mut self, window: WindowId) -> Result<()> {
+        self.backend.set_topmost(window, true)
+    }
+
+    /// 取消窗口置顶
+    pub fn unpin(&mut self, window: WindowId) -> Result<()> {
+        self.backend.set_topmost(window, false)
+    }
+
+    /// 切换前台窗口的置顶状态
+    pub fn toggle(&mut self) -> ToggleOutcome {
+        window::toggle(&mut self.backend)
+    }
+
+    /// 窗口是否置顶
+    pub fn is_pinned(&self, window: WindowId) -> bool {
+        self.backend.is_topmost(window)
+    }
+
+    /// 所有置顶窗口，按 Z 序从上到下排列
+    pub fn list_pinned(&self) -> Vec<WindowId> {
+        self.backend
+            .windows()
+            .into_iter()
+            .filter(|&w| self.backend.is_topmost(w))
+            .collect()
+    }
+
+    /// 窗口标题
+    pub fn title(&self, window: WindowId) -> Option<String> {
+        self.backend.title(window)
+    }
+}
+
+impl Pinner<Box<dyn WindowBackend>> {
+    /// 使用当前平台的窗口后端
+    pub fn native() -> Result<Self> {
+        Ok(Self::new(crate::platform::native_backend()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::sim::SimDesktop;
+
+    #[test]
+    fn lists_pinned_windows_in_z_order() {
+        let mut desktop = SimDesktop::new();
+        let a = desktop.open("a");
+        let b = desktop.open("b");
+        let c = desktop.open("c");
+
+        let mut pinner = Pinner::new(desktop);
+        pinner.pin(a).unwrap();
+        pinner.pin(c).unwrap();
+        assert_eq!(pinner.list_pinned(), vec![c, a]);
+
+        pinner.unpin(c).unwrap();
+        assert!(!pinner.is_pinned(c));
+        assert!(!pinner.is_pinned(b));
+        assert_eq!(pinner.list_pinned(), vec![a]);
+    }
+
+    #[test]
+    fn works_with_boxed_backends() {
+        let mut desktop = SimDesktop::new();
+        let window = desktop.open("窗口");
+
+        let boxed: Box<dyn WindowBackend> = Box::new(desktop);
+        let mut pinner = Pinner::new(boxed);
+        assert_eq!(
+            pinner.toggle(),
+            ToggleOutcome::Pinned {
+                title: "窗口".into()
+            }
+        );
+        assert!(pinner.is_pinned(window));
+    }
+}
@@ -0,0 +1,363 @@
+//! i3 / sway IPC 窗口操作
+//!
+//! 平铺式窗口管理器没有"置顶"，对应的是浮动 + 粘滞 (floating + sticky)。
+//! 直接通过 `$SWAYSOCK` / `$I3SOCK` 指向的 Unix 套接字收发 IPC 消息。
+
+use super::{process_from_pid, Process, WindowBackend, WindowId};
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+
+/// 消息头魔数
+const MAGIC: &[u8; 6] = b"i3-ipc";
+
+/// 消息类型
+const RUN_COMMAND: u32 = 0;
+const GET_TREE: u32 = 4;
+
+/// 编码一条 IPC 消息
+fn encode_message(kind: u32, payload: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(MAGIC.len() + 8 + payload.len());
+    message.extend_from_slice(MAGIC);
+    message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    message.extend_from_slice(&kind.to_ne_bytes());
+    message.extend_from_slice(payload);
+    message
+}
+
+/// 读取一条 IPC 消息，返回 (类型, 内容)
+fn read_message(reader: &mut impl Read) -> Result<(u32, Vec<u8>)> {
+    let mut header = [0u8; 14];
+    reader.read_exact(&mut header)?;
+    if &header[..6] != MAGIC {
+        bail!("无效的 IPC 消息头");
+    }
+    let len = u32::from_ne_bytes(header[6..10].try_into().unwrap());
+    let kind = u32::from_ne_bytes(header[10..14].try_into().unwrap());
+
+    let mut payload = vec![0u8; len as usize];
+    reader.read_exact(&mut payload)?;
+    Ok((kind, payload))
+}
+
+/// 窗口树中的叶子窗口
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Leaf {
+    id: i64,
+    title: Option<String>,
+    focused: bool,
+    floating: bool,
+    sticky: bool,
+    pid: Option<u32>,
+}
+
+/// 收集窗口树中的所有叶子窗口
+fn collect_leaves(node: &Value, parent_sticky: bool, leaves: &mut Vec<Leaf>) {
+    let sticky = parent_sticky || node["sticky"].as_bool().unwrap_or(false);
+    let children: Vec<&Value> = ["nodes", "floating_nodes"]
+        .iter()
+        .filter_map(|key| node[*key].as_array())
+        .flatten()
+        .collect();
+
+    if children.is_empty() {
+        let kind = node["type"].as_str().unwrap_or_default();
+        if kind == "con" || kind == "floating_con" {
+            // sway 用节点类型表示浮动，i3 用 floating 字段
+            let floating = kind == "floating_con"
+                || matches!(node["floating"].as_str(), Some("user_on" | "auto_on"));
+            leaves.push(Leaf {
+                id: node["id"].as_i64().unwrap_or_default(),
+                title: node["name"].as_str().map(str::to_string),
+                focused: node["focused"].as_bool().unwrap_or(false),
+                floating,
+                sticky,
+                pid: node["pid"].as_u64().map(|pid| pid as u32),
+            });
+        }
+        return;
+    }
+
+    for child in children {
+        collect_leaves(child, sticky, leaves);
+    }
+}
+
+/// 置顶/取消置顶命令
+fn pin_command(id: i64, pinned: bool) -> String {
+    if pinned {
+        format!("[con_id={}] floating enable, sticky enable", id)
+    } else {
+        format!("[con_id={}] sticky disable, floating disable", id)
+    }
+}
+
+/// i3 / sway 窗口后端
+pub struct I3Backend {
+    stream: UnixStream,
+}
+
+impl I3Backend {
+    /// 从环境变量获取 IPC 套接字路径
+    pub fn socket_path_from_env() -> Option<PathBuf> {
+        ["SWAYSOCK", "I3SOCK"]
+            .iter()
+            .filter_map(std::env::var_os)
+            .find(|path| !path.is_empty())
+            .map(PathBuf::from)
+    }
+
+    /// 连接 IPC 套接字
+    pub fn connect(path: &Path) -> Result<Self> {
+        let stream = UnixStream::connect(path)
+            .with_context(|| format!("无法连接窗口管理器 IPC: {:?}", path))?;
+        Ok(Self { stream })
+    }
+
+    /// 发送请求并读取回复
+    fn request(&self, kind: u32, payload: &str) -> Result<Value> {
+        let mut stream = &self.stream;
+        stream.write_all(&encode_message(kind, payload.as_bytes()))?;
+
+        // 跳过事件消息（最高位为 1）
+        loop {
+            let (reply_kind, body) = read_message(&mut stream)?;
+            if reply_kind & 0x8000_0000 == 0 {
+                if reply_kind != kind {
+                    bail!("IPC 回复类型不匹配: {}", reply_kind);
+                }
+                return Ok(serde_json::from_slice(&body)?);
+            }
+        }
+    }
+
+    /// 获取所有叶子窗口
+    fn leaves(&self) -> Vec<Leaf> {
+        let mut leaves = Vec::new();
+        if let Ok(tree) = self.request(GET_TREE, "") {
+            collect_leaves(&tree, false, &mut leaves);
+        }
+        leaves
+    }
+
+    fn leaf(&self, window: WindowId) -> Option<Leaf> {
+        self.leaves()
+            .into_iter()
+            .find(|leaf| leaf.id as u64 == window.0)
+    }
+}
+
+impl WindowBackend for I3Backend {
+    fn foreground(&self) -> Option<WindowId> {
+        self.leaves()
+            .into_iter()
+            .find(|leaf| leaf.focused)
+            .map(|leaf| WindowId(leaf.id as u64))
+    }
+
+    fn is_topmost(&self, window: WindowId) -> bool {
+        self.leaf(window)
+            .is_some_and(|leaf| leaf.floating && leaf.sticky)
+    }
+
+    fn set_topmost(&mut self, window: WindowId, topmost: bool) -> Result<()> {
+        let reply = self.request(RUN_COMMAND, &pin_command(window.0 as i64, topmost))?;
+
+        for outcome in reply.as_array().into_iter().flatten() {
+            if outcome["success"].as_bool() != Some(true) {
+                bail!(
+                    "窗口管理器拒绝命令: {}",
+                    outcome["error"].as_str().unwrap_or("未知错误")
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn title(&self, window: WindowId) -> Option<String> {
+        self.leaf(window)
+            .and_then(|leaf| leaf.title)
+            .filter(|t| !t.is_empty())
+    }
+
+    fn windows(&self) -> Vec<WindowId> {
+        // 窗口树没有 Z 序，浮动窗口排在前面
+        let mut leaves = self.leaves();
+        leaves.sort_by_key(|leaf| !leaf.floating);
+        leaves
+            .into_iter()
+            .map(|leaf| WindowId(leaf.id as u64))
+            .collect()
+    }
+
+    fn exists(&self, window: WindowId) -> bool {
+        self.leaf(window).is_some()
+    }
+
+    fn process(&self, window: WindowId) -> Option<Process> {
+        self.leaf(window)?.pid.and_then(process_from_pid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window::{toggle, ToggleOutcome};
+    use serde_json::json;
+    use std::os::unix::net::UnixListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    /// 模拟窗口管理器：按 GET_TREE 返回窗口树，记录收到的命令并更新状态
+    struct FakeWm {
+        path: PathBuf,
+        commands: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl FakeWm {
+        fn start(name: &str, tree: Value) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "window-topmost-{}-{}.sock",
+                name,
+                std::process::id()
+            ));
+            let _ = std::fs::remove_file(&path);
+            let listener = UnixListener::bind(&path).unwrap();
+            let commands = Arc::new(Mutex::new(Vec::new()));
+
+            let log = commands.clone();
+            thread::spawn(move || {
+                let mut tree = tree;
+                for stream in listener.incoming() {
+                    let Ok(mut stream) = stream else { break };
+                    while let Ok((kind, payload)) = read_message(&mut stream) {
+                        let reply = match kind {
+                            GET_TREE => tree.clone(),
+                            RUN_COMMAND => {
+                                let command = String::from_utf8(payload).unwrap();
+                                let pinned = command.contains("floating enable");
+                                set_focused_state(&mut tree, pinned);
+                                log.lock().unwrap().push(command);
+                                json!([{ "success": true }])
+                            }
+                            _ => json!({ "success": false }),
+                        };
+                        // 先推送一条事件，验证客户端会跳过
+                        let event = encode_message(0x8000_0003, b"{}");
+                        let body = reply.to_string();
+                        stream.write_all(&event).unwrap();
+                        stream
+                            .write_all(&encode_message(kind, body.as_bytes()))
+                            .unwrap();
+                    }
+                }
+            });
+
+            Self { path, commands }
+        }
+    }
+
+    impl Drop for FakeWm {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    fn set_focused_state(node: &mut Value, pinned: bool) {
+        if node["focused"] == json!(true) {
+            node["floating"] = json!(if pinned { "user_on" } else { "user_off" });
+            node["sticky"] = json!(pinned);
+        }
+        for key in ["nodes", "floating_nodes"] {
+            if let Some(children) = node[key].as_array_mut() {
+                children
+                    .iter_mut()
+                    .for_each(|c| set_focused_state(c, pinned));
+            }
+        }
+    }
+
+    fn i3_tree() -> Value {
+        json!({
+            "id": 1, "type": "root", "nodes": [{
+                "id": 2, "type": "output", "nodes": [{
+                    "id": 3, "type": "workspace", "nodes": [
+                        { "id": 7, "type": "con", "name": "终端", "focused": true,
+                          "floating": "auto_off", "sticky": false, "nodes": [], "floating_nodes": [] },
+                        { "id": 8, "type": "con", "name": "浏览器", "focused": false,
+                          "floating": "auto_off", "sticky": false, "nodes": [], "floating_nodes": [] }
+                    ],
+                    "floating_nodes": []
+                }]
+            }]
+        })
+    }
+
+    #[test]
+    fn message_round_trip() {
+        let encoded = encode_message(GET_TREE, b"");
+        assert_eq!(&encoded[..6], b"i3-ipc");
+        let (kind, payload) = read_message(&mut encoded.as_slice()).unwrap();
+        assert_eq!((kind, payload.len()), (GET_TREE, 0));
+        assert!(read_message(&mut &b"i3-ipd\0\0\0\0\0\0\0\0"[..]).is_err());
+    }
+
+    #[test]
+    fn finds_sway_floating_sticky_windows() {
+        let tree = json!({
+            "id": 1, "type": "root", "nodes": [{
+                "id": 3, "type": "workspace", "nodes": [],
+                "floating_nodes": [
+                    { "id": 9, "type": "floating_con", "name": "播放器", "focused": true,
+                      "sticky": true, "nodes": [], "floating_nodes": [] }
+                ]
+            }]
+        });
+        let mut leaves = Vec::new();
+        collect_leaves(&tree, false, &mut leaves);
+        assert_eq!(
+            leaves,
+            vec![Leaf {
+                id: 9,
+                title: Some("播放器".into()),
+                focused: true,
+                floating: true,
+                sticky: true,
+                pid: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn toggles_focused_container_over_ipc() {
+        let wm = FakeWm::start("toggle", i3_tree());
+        let mut backend = I3Backend::connect(&wm.path).unwrap();
+
+        assert_eq!(backend.foreground(), Some(WindowId(7)));
+        assert_eq!(
+            toggle(&mut backend),
+            ToggleOutcome::Pinned {
+                title: "终端".into()
+            }
+        );
+        assert!(backend.is_topmost(WindowId(7)));
+        assert!(!backend.is_topmost(WindowId(8)));
+        assert_eq!(backend.windows(), vec![WindowId(7), WindowId(8)]);
+
+        assert_eq!(
+            toggle(&mut backend),
+            ToggleOutcome::Unpinned {
+                title: "终端".into()
+            }
+        );
+        assert_eq!(
+            *wm.commands.lock().unwrap(),
+            vec![
+                "[con_id=7] floating enable, sticky enable".to_string(),
+                "[con_id=7] sticky disable, floating disable".to_string(),
+            ]
+        );
+    }
+}
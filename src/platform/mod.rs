@@ -0,0 +1,133 @@
+//! 平台相关实现
+
+use anyhow::Result;
+
+#[cfg(unix)]
+pub mod i3;
+pub mod sim;
+#[cfg(windows)]
+pub mod windows;
+#[cfg(unix)]
+pub mod x11;
+
+/// 扫描码在当前键盘布局下的虚拟键码
+pub fn scan_code_vk(scan: u16) -> Option<u32> {
+    #[cfg(windows)]
+    return windows::scan_code_vk(scan);
+    #[cfg(not(windows))]
+    {
+        let _ = scan;
+        None
+    }
+}
+
+/// 扫描码在当前键盘布局下的按键名称（如德语布局上的 "Ö"）
+pub fn scan_code_label(scan: u16) -> Option<String> {
+    #[cfg(windows)]
+    return windows::scan_code_label(scan);
+    #[cfg(not(windows))]
+    {
+        let _ = scan;
+        None
+    }
+}
+
+/// 窗口标识（各平台句柄统一转换为整数）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct WindowId(pub u64);
+
+/// 窗口所属进程
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Process {
+    /// 进程 ID
+    pub pid: u32,
+    /// 进程名（如 "notepad.exe"）
+    pub name: String,
+}
+
+/// 窗口后端：置顶逻辑所需的全部窗口系统操作
+pub trait WindowBackend {
+    /// 当前前台窗口
+    fn foreground(&self) -> Option<WindowId>;
+
+    /// 窗口是否置顶
+    fn is_topmost(&self, window: WindowId) -> bool;
+
+    /// 设置/取消置顶
+    fn set_topmost(&mut self, window: WindowId, topmost: bool) -> Result<()>;
+
+    /// 窗口标题（无标题时返回 None）
+    fn title(&self, window: WindowId) -> Option<String>;
+
+    /// 枚举顶层窗口，按 Z 序从上到下排列
+    fn windows(&self) -> Vec<WindowId>;
+
+    /// 窗口是否仍然存在
+    fn exists(&self, window: WindowId) -> bool {
+        self.windows().contains(&window)
+    }
+
+    /// 窗口所属进程（后端不支持时返回 None）
+    fn process(&self, _window: WindowId) -> Option<Process> {
+        None
+    }
+}
+
+impl<T: WindowBackend + ?Sized> WindowBackend for Box<T> {
+    fn foreground(&self) -> Option<WindowId> {
+        (**self).foreground()
+    }
+
+    fn is_topmost(&self, window: WindowId) -> bool {
+        (**self).is_topmost(window)
+    }
+
+    fn set_topmost(&mut self, window: WindowId, topmost: bool) -> Result<()> {
+        (**self).set_topmost(window, topmost)
+    }
+
+    fn title(&self, window: WindowId) -> Option<String> {
+        (**self).title(window)
+    }
+
+    fn windows(&self) -> Vec<WindowId> {
+        (**self).windows()
+    }
+
+    fn exists(&self, window: WindowId) -> bool {
+        (**self).exists(window)
+    }
+
+    fn process(&self, window: WindowId) -> Option<Process> {
+        (**self).process(window)
+    }
+}
+
+/// 按进程 ID 读取进程名（Linux 下读取 /proc）
+#[cfg(unix)]
+fn process_from_pid(pid: u32) -> Option<Process> {
+    let name = std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok()?;
+    Some(Process {
+        pid,
+        name: name.trim_end().to_string(),
+    })
+}
+
+/// 当前平台的窗口后端
+///
+/// Windows 使用 Win32；其他平台在 i3 / sway 下（设置了 `$SWAYSOCK` 或 `$I3SOCK`）
+/// 使用 IPC，否则使用 X11 / EWMH。
+pub fn native_backend() -> Result<Box<dyn WindowBackend>> {
+    #[cfg(windows)]
+    {
+        Ok(Box::new(windows::Win32Backend))
+    }
+
+    #[cfg(not(windows))]
+    {
+        match i3::I3Backend::socket_path_from_env() {
+            Some(path) => Ok(Box::new(i3::I3Backend::connect(&path)?)),
+            None => Ok(Box::new(x11::X11Backend::connect()?)),
+        }
+    }
+}
@@ -0,0 +1,299 @@
+//! 模拟桌面（内存中的窗口系统，用于测试）
+//!
+//! 按 Win32 的规则维护 Z 序：置顶窗口始终位于普通窗口之上，
+//! 被拥有的窗口始终位于其所有者之上。
+
+use super::{Process, WindowBackend, WindowId};
+use anyhow::{bail, Result};
+use std::collections::HashSet;
+
+/// 模拟窗口
+#[derive(Debug, Clone)]
+struct SimWindow {
+    id: WindowId,
+    title: String,
+    owner: Option<WindowId>,
+    topmost: bool,
+    process: Option<Process>,
+}
+
+/// 模拟桌面
+#[derive(Debug, Default)]
+pub struct SimDesktop {
+    /// 按 Z 序从上到下排列
+    windows: Vec<SimWindow>,
+    foreground: Option<WindowId>,
+    next_id: u64,
+    /// 拒绝修改置顶状态的窗口（如权限更高的进程）
+    protected: HashSet<WindowId>,
+    /// 接下来若干次 set_topmost 调用失败
+    fail_next: usize,
+}
+
+impl SimDesktop {
+    /// 创建空桌面
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 打开新窗口并激活
+    pub fn open(&mut self, title: &str) -> WindowId {
+        self.open_window(title, None)
+    }
+
+    /// 打开被 `owner` 拥有的窗口（如对话框）并激活
+    pub fn open_owned(&mut self, owner: WindowId, title: &str) -> WindowId {
+        self.open_window(title, Some(owner))
+    }
+
+    fn open_window(&mut self, title: &str, owner: Option<WindowId>) -> WindowId {
+        self.next_id += 1;
+        let id = WindowId(self.next_id);
+        let topmost = owner.is_some_and(|o| self.is_topmost(o));
+        self.windows.insert(
+            0,
+            SimWindow {
+                id,
+                title: title.to_string(),
+                owner,
+                topmost,
+                process: None,
+            },
+        );
+        self.focus(id);
+        id
+    }
+
+    /// 关闭窗口（连同其拥有的窗口）
+    pub fn close(&mut self, window: WindowId) {
+        let group = self.owned_group(window);
+        self.windows.retain(|w| !group.contains(&w.id));
+        self.protected.retain(|w| !group.contains(w));
+        if self.foreground.is_some_and(|f| group.contains(&f)) {
+            self.foreground = None;
+        }
+    }
+
+    /// 激活窗口，将其提到所在层的最上方
+    pub fn focus(&mut self, window: WindowId) {
+        if self.index_of(window).is_some() {
+            self.raise(window);
+            self.foreground = Some(window);
+        }
+    }
+
+    /// 取消激活（如点击桌面）
+    pub fn clear_focus(&mut self) {
+        self.foreground = None;
+    }
+
+    /// 修改窗口标题
+    pub fn set_title(&mut self, window: WindowId, title: &str) {
+        if let Some(i) = self.index_of(window) {
+            self.windows[i].title = title.to_string();
+        }
+    }
+
+    /// 设置窗口所属进程
+    pub fn set_process(&mut self, window: WindowId, pid: u32, name: &str) {
+        if let Some(i) = self.index_of(window) {
+            self.windows[i].process = Some(Process {
+                pid,
+                name: name.to_string(),
+            });
+        }
+    }
+
+    /// 让窗口拒绝置顶修改
+    pub fn protect(&mut self, window: WindowId) {
+        self.protected.insert(window);
+    }
+
+    /// 让接下来 `count` 次 set_topmost 调用失败
+    pub fn fail_next(&mut self, count: usize) {
+        self.fail_next = count;
+    }
+
+    /// 当前 Z 序（从上到下）
+    pub fn z_order(&self) -> Vec<WindowId> {
+        self.windows.iter().map(|w| w.id).collect()
+    }
+
+    fn index_of(&self, window: WindowId) -> Option<usize> {
+        self.windows.iter().position(|w| w.id == window)
+    }
+
+    fn owner_of(&self, window: WindowId) -> Option<WindowId> {
+        self.index_of(window).and_then(|i| self.windows[i].owner)
+    }
+
+    /// 窗口及其直接/间接拥有的窗口
+    fn owned_group(&self, window: WindowId) -> Vec<WindowId> {
+        self.owned_levels(window)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// 同 `owned_group`，附带相对 `window` 的拥有层级
+    fn owned_levels(&self, window: WindowId) -> Vec<(WindowId, usize)> {
+        let mut group = vec![(window, 0)];
+        let mut i = 0;
+        while i < group.len() {
+            let (parent, level) = group[i];
+            group.extend(
+                self.windows
+                    .iter()
+                    .filter(|w| w.owner == Some(parent))
+                    .map(|w| (w.id, level + 1)),
+            );
+            i += 1;
+        }
+        group
+    }
+
+    /// 将窗口及其拥有的窗口提到所在层的最上方
+    fn raise(&mut self, window: WindowId) {
+        let levels = self.owned_levels(window);
+        let level_of = |id: WindowId| levels.iter().find(|(w, _)| *w == id).map(|(_, l)| *l);
+
+        let (mut moved, rest): (Vec<_>, Vec<_>) = std::mem::take(&mut self.windows)
+            .into_iter()
+            .partition(|w| level_of(w.id).is_some());
+
+        // 被拥有的窗口排在所有者之上，同一层级保持原有顺序
+        moved.sort_by_key(|w| std::cmp::Reverse(level_of(w.id)));
+
+        let topmost = moved
+            .iter()
+            .find(|w| w.id == window)
+            .is_some_and(|w| w.topmost);
+        let at = if topmost {
+            0
+        } else {
+            rest.iter().take_while(|w| w.topmost).count()
+        };
+
+        self.windows = rest;
+        self.windows.splice(at..at, moved);
+    }
+}
+
+impl WindowBackend for SimDesktop {
+    fn foreground(&self) -> Option<WindowId> {
+        self.foreground
+    }
+
+    fn is_topmost(&self, window: WindowId) -> bool {
+        self.index_of(window)
+            .is_some_and(|i| self.windows[i].topmost)
+    }
+
+    fn set_topmost(&mut self, window: WindowId, topmost: bool) -> Result<()> {
+        if self.index_of(window).is_none() {
+            bail!("窗口不存在: {:?}", window);
+        }
+        if self.fail_next > 0 {
+            self.fail_next -= 1;
+            bail!("模拟失败");
+        }
+        if self.protected.contains(&window) {
+            bail!("拒绝访问");
+        }
+
+        // 置顶时被拥有的窗口一并置顶；取消置顶时所有者链也一并取消
+        let mut anchor = window;
+        if !topmost {
+            while let Some(owner) = self.owner_of(anchor) {
+                anchor = owner;
+            }
+        }
+        let affected = self.owned_group(anchor);
+        for w in self.windows.iter_mut() {
+            if affected.contains(&w.id) {
+                w.topmost = topmost;
+            }
+        }
+
+        self.raise(anchor);
+        Ok(())
+    }
+
+    fn title(&self, window: WindowId) -> Option<String> {
+        self.index_of(window)
+            .map(|i| self.windows[i].title.clone())
+            .filter(|t| !t.is_empty())
+    }
+
+    fn windows(&self) -> Vec<WindowId> {
+        self.z_order()
+    }
+
+    fn exists(&self, window: WindowId) -> bool {
+        self.index_of(window).is_some()
+    }
+
+    fn process(&self, window: WindowId) -> Option<Process> {
+        self.index_of(window)
+            .and_then(|i| self.windows[i].process.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn topmost_windows_stay_above_focused_ones() {
+        let mut desktop = SimDesktop::new();
+        let editor = desktop.open("编辑器");
+        let browser = desktop.open("浏览器");
+
+        desktop.set_topmost(editor, true).unwrap();
+        assert_eq!(desktop.z_order(), vec![editor, browser]);
+
+        desktop.focus(browser);
+        assert_eq!(desktop.foreground(), Some(browser));
+        assert_eq!(desktop.z_order(), vec![editor, browser]);
+
+        desktop.set_topmost(editor, false).unwrap();
+        assert_eq!(desktop.z_order(), vec![editor, browser]);
+        desktop.focus(browser);
+        assert_eq!(desktop.z_order(), vec![browser, editor]);
+    }
+
+    #[test]
+    fn owned_windows_follow_their_owner() {
+        let mut desktop = SimDesktop::new();
+        let main = desktop.open("主窗口");
+        let dialog = desktop.open_owned(main, "对话框");
+        let other = desktop.open("其他");
+
+        desktop.set_topmost(main, true).unwrap();
+        assert!(desktop.is_topmost(dialog));
+        assert_eq!(desktop.z_order(), vec![dialog, main, other]);
+
+        // 取消被拥有窗口的置顶会连带所有者
+        desktop.set_topmost(dialog, false).unwrap();
+        assert!(!desktop.is_topmost(main));
+        assert_eq!(desktop.z_order(), vec![dialog, main, other]);
+
+        desktop.close(main);
+        assert_eq!(desktop.z_order(), vec![other]);
+    }
+
+    #[test]
+    fn injected_failures() {
+        let mut desktop = SimDesktop::new();
+        let admin = desktop.open("管理员窗口");
+        desktop.protect(admin);
+        assert!(desktop.set_topmost(admin, true).is_err());
+        assert!(!desktop.is_topmost(admin));
+
+        let plain = desktop.open("普通窗口");
+        desktop.fail_next(1);
+        assert!(desktop.set_topmost(plain, true).is_err());
+        assert!(desktop.set_topmost(plain, true).is_ok());
+        assert!(desktop.set_topmost(WindowId(999), true).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+//! Win32 窗口操作
+
+use super::{Process, WindowBackend, WindowId};
+use anyhow::Result;
+use windows::core::PWSTR;
+use windows::Win32::Foundation::{CloseHandle, BOOL, HWND, LPARAM};
+use windows::Win32::System::Threading::{
+    OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    EnumWindows, GetForegroundWindow, GetWindowLongW, GetWindowTextLengthW, GetWindowTextW,
+    GetWindowThreadProcessId, IsWindow, IsWindowVisible, SetWindowPos, GWL_EXSTYLE, HWND_NOTOPMOST,
+    HWND_TOPMOST, SWP_NOMOVE, SWP_NOSIZE, WS_EX_TOPMOST,
+};
+
+/// Win32 窗口后端
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Win32Backend;
+
+/// WindowId → HWND
+pub fn hwnd(window: WindowId) -> HWND {
+    HWND(window.0 as usize as *mut _)
+}
+
+/// HWND → WindowId
+pub fn window_id(hwnd: HWND) -> WindowId {
+    WindowId(hwnd.0 as usize as u64)
+}
+
+impl WindowBackend for Win32Backend {
+    fn foreground(&self) -> Option<WindowId> {
+        let hwnd = unsafe { GetForegroundWindow() };
+        if hwnd.0.is_null() {
+            None
+        } else {
+            Some(window_id(hwnd))
+        }
+    }
+
+    fn is_topmost(&self, window: WindowId) -> bool {
+        let ex_style = unsafe { GetWindowLongW(hwnd(window), GWL_EXSTYLE) } as u32;
+        (ex_style & WS_EX_TOPMOST.0) != 0
+    }
+
+    fn set_topmost(&mut self, window: WindowId, topmost: bool) -> Result<()> {
+        let insert_after = if topmost {
+            HWND_TOPMOST
+        } else {
+            HWND_NOTOPMOST
+        };
+        unsafe {
+            SetWindowPos(
+                hwnd(window),
+                insert_after,
+                0,
+                0,
+                0,
+                0,
+                SWP_NOMOVE | SWP_NOSIZE,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn title(&self, window: WindowId) -> Option<String> {
+        unsafe {
+            let hwnd = hwnd(window);
+            let len = GetWindowTextLengthW(hwnd);
+            if len == 0 {
+                return None;
+            }
+
+            let mut buffer = vec![0u16; (len + 1) as usize];
+            let copied = GetWindowTextW(hwnd, &mut buffer);
+
+            if copied > 0 {
+                Some(String::from_utf16_lossy(&buffer[..copied as usize]))
+            } else {
+                None
+            }
+        }
+    }
+
+    fn windows(&self) -> Vec<WindowId> {
+        unsafe extern "system" fn collect(hwnd: HWND, lparam: LPARAM) -> BOOL {
+            let windows = &mut *(lparam.0 as *mut Vec<WindowId>);
+            if IsWindowVisible(hwnd).as_bool() {
+                windows.push(window_id(hwnd));
+            }
+            BOOL(1)
+        }
+
+        // EnumWindows 按 Z 序从上到下枚举
+        let mut windows = Vec::new();
+        unsafe {
+            let _ = EnumWindows(Some(collect), LPARAM(&mut windows as *mut _ as isize));
+        }
+        windows
+    }
+
+    fn exists(&self, window: WindowId) -> bool {
+        unsafe { IsWindow(hwnd(window)).as_bool() }
+    }
+
+    fn process(&self, window: WindowId) -> Option<Process> {
+        unsafe {
+            let mut pid = 0u32;
+            GetWindowThreadProcessId(hwnd(window), Some(&mut pid));
+            if pid == 0 {
+                return None;
+            }
+
+            let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+            let mut buffer = [0u16; 260];
+            let mut len = buffer.len() as u32;
+            let result = QueryFullProcessImageNameW(
+                handle,
+                PROCESS_NAME_WIN32,
+                PWSTR(buffer.as_mut_ptr()),
+                &mut len,
+            );
+            let _ = CloseHandle(handle);
+            result.ok()?;
+
+            // 只保留文件名
+            let path = String::from_utf16_lossy(&buffer[..len as usize]);
+            let name = path.rsplit('\\').next().unwrap_or(&path).to_string();
+            Some(Process { pid, name })
+        }
+    }
+}
+
+/// 扫描码在当前键盘布局下的虚拟键码
+pub fn scan_code_vk(scan: u16) -> Option<u32> {
+    use windows::Win32::UI::Input::KeyboardAndMouse::{MapVirtualKeyW, MAPVK_VSC_TO_VK_EX};
+
+    let vk = unsafe { MapVirtualKeyW(scan as u32, MAPVK_VSC_TO_VK_EX) };
+    (vk != 0).then_some(vk)
+}
+
+/// 扫描码在当前键盘布局下的按键名称
+pub fn scan_code_label(scan: u16) -> Option<String> {
+    use windows::Win32::UI::Input::KeyboardAndMouse::GetKeyNameTextW;
+
+    // 参数格式与 WM_KEYDOWN 的 LPARAM 相同：16-23 位为扫描码，24 位表示扩展键
+    let mut lparam = i32::from(scan as u8) << 16;
+    if scan & 0xFF00 == 0xE000 {
+        lparam |= 1 << 24;
+    }
+    let mut buffer = [0u16; 64];
+    let len = unsafe { GetKeyNameTextW(lparam, &mut buffer) };
+    (len > 0).then(|| String::from_utf16_lossy(&buffer[..len as usize]))
+}
+
+/// 播放提示音
+pub fn play_beep(frequency: u32, duration: u32) {
+    use windows::Win32::System::Diagnostics::Debug::Beep;
+
+    unsafe {
+        let _ = Beep(frequency, duration);
+    }
+}
@@ -0,0 +1,274 @@
+//! X11 / EWMH 窗口操作
+//!
+//! 通过 `_NET_ACTIVE_WINDOW` 获取前台窗口，向根窗口发送 `_NET_WM_STATE`
+//! 客户端消息切换 `_NET_WM_STATE_ABOVE`，由窗口管理器完成实际置顶。
+
+use super::{process_from_pid, Process, WindowBackend, WindowId};
+use anyhow::{Context, Result};
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{
+    AtomEnum, ClientMessageEvent, ConnectionExt as _, EventMask, Window,
+};
+use x11rb::rust_connection::RustConnection;
+
+x11rb::atom_manager! {
+    /// 用到的 EWMH 原子
+    pub Atoms: AtomsCookie {
+        _NET_ACTIVE_WINDOW,
+        _NET_CLIENT_LIST_STACKING,
+        _NET_WM_NAME,
+        _NET_WM_PID,
+        _NET_WM_STATE,
+        _NET_WM_STATE_ABOVE,
+        UTF8_STRING,
+    }
+}
+
+/// `_NET_WM_STATE` 消息中的操作
+const NET_WM_STATE_REMOVE: u32 = 0;
+const NET_WM_STATE_ADD: u32 = 1;
+
+/// 消息来源：普通应用程序
+const SOURCE_APPLICATION: u32 = 1;
+
+/// X11 窗口后端
+pub struct X11Backend {
+    conn: RustConnection,
+    root: Window,
+    atoms: Atoms,
+}
+
+impl X11Backend {
+    /// 连接 `$DISPLAY` 指定的 X 服务器
+    pub fn connect() -> Result<Self> {
+        let (conn, screen) = x11rb::connect(None).context("无法连接 X 服务器")?;
+        let root = conn.setup().roots[screen].root;
+        let atoms = Atoms::new(&conn)?.reply()?;
+        Ok(Self { conn, root, atoms })
+    }
+
+    /// 读取 32 位格式的属性
+    fn property32(&self, window: Window, property: u32, type_: impl Into<u32>) -> Vec<u32> {
+        let reply = self
+            .conn
+            .get_property(false, window, property, type_, 0, u32::MAX)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok());
+        reply
+            .and_then(|r| r.value32().map(|v| v.collect()))
+            .unwrap_or_default()
+    }
+
+    /// 读取字符串属性
+    fn property_string(
+        &self,
+        window: Window,
+        property: u32,
+        type_: impl Into<u32>,
+    ) -> Option<String> {
+        let reply = self
+            .conn
+            .get_property(false, window, property, type_, 0, u32::MAX)
+            .ok()?
+            .reply()
+            .ok()?;
+        if reply.value.is_empty() {
+            return None;
+        }
+        Some(String::from_utf8_lossy(&reply.value).into_owned())
+    }
+}
+
+/// 构造切换 `_NET_WM_STATE_ABOVE` 的客户端消息
+fn above_state_message(atoms: &Atoms, window: Window, above: bool) -> ClientMessageEvent {
+    let action = if above {
+        NET_WM_STATE_ADD
+    } else {
+        NET_WM_STATE_REMOVE
+    };
+    ClientMessageEvent::new(
+        32,
+        window,
+        atoms._NET_WM_STATE,
+        [action, atoms._NET_WM_STATE_ABOVE, 0, SOURCE_APPLICATION, 0],
+    )
+}
+
+impl WindowBackend for X11Backend {
+    fn foreground(&self) -> Option<WindowId> {
+        self.property32(self.root, self.atoms._NET_ACTIVE_WINDOW, AtomEnum::WINDOW)
+            .first()
+            .filter(|&&w| w != 0)
+            .map(|&w| WindowId(w.into()))
+    }
+
+    fn is_topmost(&self, window: WindowId) -> bool {
+        self.property32(window.0 as Window, self.atoms._NET_WM_STATE, AtomEnum::ATOM)
+            .contains(&self.atoms._NET_WM_STATE_ABOVE)
+    }
+
+    fn set_topmost(&mut self, window: WindowId, topmost: bool) -> Result<()> {
+        let event = above_state_message(&self.atoms, window.0 as Window, topmost);
+        self.conn.send_event(
+            false,
+            self.root,
+            EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+            event,
+        )?;
+        self.conn.flush()?;
+        Ok(())
+    }
+
+    fn title(&self, window: WindowId) -> Option<String> {
+        let window = window.0 as Window;
+        self.property_string(window, self.atoms._NET_WM_NAME, self.atoms.UTF8_STRING)
+            .or_else(|| self.property_string(window, AtomEnum::WM_NAME.into(), AtomEnum::STRING))
+    }
+
+    fn windows(&self) -> Vec<WindowId> {
+        // _NET_CLIENT_LIST_STACKING 按从下到上排列
+        let mut windows: Vec<_> = self
+            .property32(
+                self.root,
+                self.atoms._NET_CLIENT_LIST_STACKING,
+                AtomEnum::WINDOW,
+            )
+            .into_iter()
+            .map(|w| WindowId(w.into()))
+            .collect();
+        windows.reverse();
+        windows
+    }
+
+    fn exists(&self, window: WindowId) -> bool {
+        self.conn
+            .get_window_attributes(window.0 as Window)
+            .ok()
+            .is_some_and(|cookie| cookie.reply().is_ok())
+    }
+
+    fn process(&self, window: WindowId) -> Option<Process> {
+        let pid = *self
+            .property32(
+                window.0 as Window,
+                self.atoms._NET_WM_PID,
+                AtomEnum::CARDINAL,
+            )
+            .first()?;
+        process_from_pid(pid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fake_atoms() -> Atoms {
+        Atoms {
+            _NET_ACTIVE_WINDOW: 1,
+            _NET_CLIENT_LIST_STACKING: 2,
+            _NET_WM_NAME: 3,
+            _NET_WM_PID: 7,
+            _NET_WM_STATE: 4,
+            _NET_WM_STATE_ABOVE: 5,
+            UTF8_STRING: 6,
+        }
+    }
+
+    #[test]
+    fn above_message_follows_ewmh_layout() {
+        let atoms = fake_atoms();
+        let add = above_state_message(&atoms, 0x40_0001, true);
+        assert_eq!(add.format, 32);
+        assert_eq!(add.window, 0x40_0001);
+        assert_eq!(add.type_, atoms._NET_WM_STATE);
+        assert_eq!(add.data.as_data32(), [1, 5, 0, 1, 0]);
+
+        let remove = above_state_message(&atoms, 0x40_0001, false);
+        assert_eq!(remove.data.as_data32()[0], 0);
+    }
+
+    /// 需要 X 服务器和支持 EWMH 的窗口管理器，例如：
+    ///
+    /// ```sh
+    /// xvfb-run -a sh -c 'openbox & sleep 1; cargo test -- --ignored x11'
+    /// ```
+    #[test]
+    #[ignore]
+    fn toggles_active_window_under_ewmh_window_manager() {
+        use crate::window::{toggle, ToggleOutcome};
+        use std::time::{Duration, Instant};
+        use x11rb::protocol::xproto::{CreateWindowAux, PropMode, WindowClass};
+        use x11rb::wrapper::ConnectionExt as _;
+        use x11rb::COPY_DEPTH_FROM_PARENT;
+
+        let mut backend = X11Backend::connect().expect("需要设置 DISPLAY");
+        let conn = &backend.conn;
+        let window = conn.generate_id().unwrap();
+        conn.create_window(
+            COPY_DEPTH_FROM_PARENT,
+            window,
+            backend.root,
+            0,
+            0,
+            200,
+            100,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            0,
+            &CreateWindowAux::new(),
+        )
+        .unwrap();
+        conn.change_property8(
+            PropMode::REPLACE,
+            window,
+            backend.atoms._NET_WM_NAME,
+            backend.atoms.UTF8_STRING,
+            "置顶测试".as_bytes(),
+        )
+        .unwrap();
+        conn.map_window(window).unwrap();
+
+        // 请求窗口管理器激活窗口
+        let activate = ClientMessageEvent::new(
+            32,
+            window,
+            backend.atoms._NET_ACTIVE_WINDOW,
+            [SOURCE_APPLICATION, 0, 0, 0, 0],
+        );
+        conn.send_event(
+            false,
+            backend.root,
+            EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+            activate,
+        )
+        .unwrap();
+        conn.flush().unwrap();
+
+        let id = WindowId(window.into());
+        let wait_until = |backend: &X11Backend, cond: &dyn Fn(&X11Backend) -> bool| {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while !cond(backend) {
+                assert!(Instant::now() < deadline, "等待窗口管理器超时");
+                std::thread::sleep(Duration::from_millis(20));
+            }
+        };
+
+        wait_until(&backend, &|b| b.foreground() == Some(id));
+        assert_eq!(
+            toggle(&mut backend),
+            ToggleOutcome::Pinned {
+                title: "置顶测试".into()
+            }
+        );
+        wait_until(&backend, &|b| b.is_topmost(id));
+
+        assert_eq!(
+            toggle(&mut backend),
+            ToggleOutcome::Unpinned {
+                title: "置顶测试".into()
+            }
+        );
+        wait_until(&backend, &|b| !b.is_topmost(id));
+    }
+}
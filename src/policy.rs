@@ -0,0 +1,357 @@
+//! 管理员策略
+//!
+//! 策略文件（见 [`location::policy_file`]）由管理员维护，优先于所有配置层：
+//!
+//! ```toml
+//! # 锁定的设置，用户配置中不能改成其他值
+//! [settings]
+//! play_sound = false
+//! max_pinned = 3
+//!
+//! # 禁止绑定的快捷键和动作
+//! [forbid]
+//! hotkeys = ["Ctrl+Alt+T"]
+//! actions = ["exit"]
+//! ```
+//!
+//! [`location::policy_file`]: crate::location::policy_file
+
+use crate::accelerator::Accelerator;
+use crate::action::Action;
+use crate::config::{line_column, Config, HotkeyConfig, Settings};
+use crate::diagnostics::{Diagnostic, Severity, SETTINGS_FIELDS};
+use crate::error::ConfigError;
+use serde::Deserialize;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+use toml_edit::{ImDocument, Item, TableLike};
+
+/// 禁止的快捷键和动作
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Forbid {
+    #[serde(default)]
+    hotkeys: Vec<HotkeyConfig>,
+    #[serde(default)]
+    actions: Vec<Action>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyFile {
+    #[serde(default)]
+    settings: Table,
+    #[serde(default)]
+    forbid: Forbid,
+}
+
+/// 管理员策略
+#[derive(Debug, Clone, Default)]
+pub struct Policy {
+    /// 策略文件，没有策略时为 None
+    path: Option<PathBuf>,
+    /// 锁定的设置
+    locked: Table,
+    forbidden_hotkeys: Vec<Accelerator>,
+    forbidden_actions: Vec<Action>,
+}
+
+impl Policy {
+    /// 读取策略文件，不存在时返回空策略
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        match fs::read_to_string(path) {
+            Ok(content) => Self::parse(&content, path),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(source) => Err(ConfigError::Read {
+                path: path.to_path_buf(),
+                source,
+            }),
+        }
+    }
+
+    /// 解析策略内容，`path` 用于错误信息
+    pub fn parse(content: &str, path: &Path) -> Result<Self, ConfigError> {
+        let error = |offset: usize, message: String| {
+            let (line, column) = line_column(content, offset);
+            ConfigError::Parse {
+                path: path.to_path_buf(),
+                line,
+                column,
+                message,
+            }
+        };
+        let file: PolicyFile = toml::from_str(content)
+            .map_err(|e| error(e.span().map_or(0, |s| s.start), e.message().to_string()))?;
+
+        // 锁定的值必须是有效的设置
+        if let Some(name) = file
+            .settings
+            .keys()
+            .find(|name| !SETTINGS_FIELDS.contains(&name.as_str()))
+        {
+            return Err(error(0, format!("[settings] 未知的字段 \"{}\"", name)));
+        }
+        Settings::deserialize(Value::Table(file.settings.clone()))
+            .map_err(|e| error(0, format!("[settings] {}", e.message())))?;
+
+        let forbidden_hotkeys = file
+            .forbid
+            .hotkeys
+            .iter()
+            .map(HotkeyConfig::accelerator)
+            .collect::<Result<_, _>>()
+            .map_err(|e| error(0, format!("[forbid] {}", e)))?;
+
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            locked: file.settings,
+            forbidden_hotkeys,
+            forbidden_actions: file.forbid.actions,
+        })
+    }
+
+    /// 策略文件
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// 没有任何限制
+    pub fn is_empty(&self) -> bool {
+        self.locked.is_empty()
+            && self.forbidden_hotkeys.is_empty()
+            && self.forbidden_actions.is_empty()
+    }
+
+    /// 锁定的设置名称
+    pub fn locked(&self) -> impl Iterator<Item = &str> {
+        self.locked.keys().map(String::as_str)
+    }
+
+    /// 设置是否被锁定，`name` 为 `[settings]` 中的字段名
+    pub fn is_locked(&self, name: &str) -> bool {
+        self.locked.contains_key(name)
+    }
+
+    /// 动作是否被禁止
+    pub fn forbids_action(&self, action: Action) -> bool {
+        self.forbidden_actions.contains(&action)
+    }
+
+    /// 快捷键是否被禁止，会被同一次按键触发的都算（禁止 Ctrl+T 时 LCtrl+T 也被禁止）
+    pub fn forbids_hotkey(&self, accel: &Accelerator) -> bool {
+        self.forbidden_hotkeys.iter().any(|f| f.overlaps(accel))
+    }
+
+    /// 禁止的快捷键
+    pub fn forbidden_hotkeys(&self) -> &[Accelerator] {
+        &self.forbidden_hotkeys
+    }
+
+    /// 禁止的动作
+    pub fn forbidden_actions(&self) -> &[Action] {
+        &self.forbidden_actions
+    }
+
+    /// 把锁定的值写入设置
+    pub fn apply(&self, settings: &mut Settings) {
+        if self.locked.is_empty() {
+            return;
+        }
+        let Ok(Value::Table(mut table)) = Value::try_from(&*settings) else {
+            return;
+        };
+        table.extend(self.locked.clone());
+        if let Ok(locked) = Settings::deserialize(Value::Table(table)) {
+            *settings = locked;
+        }
+    }
+
+    /// 检查一个配置文件是否违反策略：修改锁定的设置、绑定禁止的快捷键或动作
+    ///
+    /// `content` 应当已经通过 [`Config::parse`] 的检查。
+    pub fn check(&self, content: &str, path: &Path) -> Vec<Diagnostic> {
+        let mut found = Vec::new();
+        if self.is_empty() {
+            return found;
+        }
+        let (Ok(doc), Ok(config)) = (ImDocument::parse(content), Config::deserialize_str(content))
+        else {
+            return found;
+        };
+        let mut push = |span: Option<Range<usize>>, message: String| {
+            let (line, column) = line_column(content, span.map_or(0, |s| s.start));
+            found.push(Diagnostic {
+                severity: Severity::Error,
+                path: path.to_path_buf(),
+                line,
+                column,
+                message,
+                suggestion: None,
+            });
+        };
+        let root = doc.as_table();
+
+        if let Some(settings) = root.get("settings").and_then(Item::as_table_like) {
+            for (name, locked) in &self.locked {
+                let Some((_, item)) = settings.get_key_value(name) else {
+                    continue;
+                };
+                if !same_value(item, locked) {
+                    push(
+                        item.span(),
+                        format!("设置 {} 已被管理员锁定为 {}", name, locked),
+                    );
+                }
+            }
+        }
+
+        // (快捷键所在的项, 动作所在的项, 快捷键, 动作)
+        let mut entries = Vec::new();
+        if let Some(item) = root.get("hotkey") {
+            entries.push((Some(item), None, &config.hotkey, Action::Toggle));
+        }
+        for (table, binding) in array_tables(root, "bindings").iter().zip(&config.bindings) {
+            entries.push((
+                table.get("hotkey"),
+                table.get("action"),
+                &binding.hotkey,
+                binding.action,
+            ));
+        }
+
+        for (hotkey_item, action_item, hotkey, action) in entries {
+            if self.forbids_action(action) {
+                let span = action_item.or(hotkey_item).and_then(Item::span);
+                push(span, format!("动作 {} 已被管理员禁止", action.name()));
+            }
+            for accel in hotkey.candidates().unwrap_or_default() {
+                if self.forbids_hotkey(&accel) {
+                    let span = hotkey_item.and_then(Item::span);
+                    push(span, format!("快捷键 {} 已被管理员禁止", accel));
+                }
+            }
+        }
+
+        for (table, chord) in array_tables(root, "chords").iter().zip(&config.chords) {
+            let leader = table.get("leader");
+            for accel in chord.leader.candidates().unwrap_or_default() {
+                if self.forbids_hotkey(&accel) {
+                    push(
+                        leader.and_then(Item::span),
+                        format!("快捷键 {} 已被管理员禁止", accel),
+                    );
+                }
+            }
+            let keys = table.get("keys").and_then(Item::as_table_like);
+            for (key, action) in &chord.keys {
+                if self.forbids_action(*action) {
+                    let span = keys
+                        .and_then(|k| k.get(key))
+                        .or(leader)
+                        .and_then(Item::span);
+                    push(span, format!("动作 {} 已被管理员禁止", action.name()));
+                }
+            }
+        }
+        found
+    }
+}
+
+/// 配置文件中的值是否与锁定的值相同（设置只有布尔、整数和字符串）
+fn same_value(item: &Item, locked: &Value) -> bool {
+    match locked {
+        Value::Boolean(b) => item.as_bool() == Some(*b),
+        Value::Integer(i) => item.as_integer() == Some(*i),
+        Value::String(s) => item.as_str() == Some(s.as_str()),
+        _ => false,
+    }
+}
+
+/// 表数组（如 `[[bindings]]`）中的每个表
+fn array_tables<'a>(root: &'a toml_edit::Table, key: &str) -> Vec<&'a dyn TableLike> {
+    match root.get(key) {
+        Some(Item::ArrayOfTables(array)) => array.iter().map(|t| t as &dyn TableLike).collect(),
+        Some(Item::Value(toml_edit::Value::Array(array))) => array
+            .iter()
+            .filter_map(|v| v.as_inline_table())
+            .map(|t| t as &dyn TableLike)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: &str = r#"
+[settings]
+play_sound = false
+max_pinned = 3
+
+[forbid]
+hotkeys = ["Ctrl+Alt+T"]
+actions = ["exit"]
+"#;
+
+    fn policy() -> Policy {
+        Policy::parse(POLICY, Path::new("policy.toml")).unwrap()
+    }
+
+    fn messages(content: &str) -> Vec<String> {
+        policy()
+            .check(content, Path::new("config.toml"))
+            .iter()
+            .map(|d| d.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn locked_settings_override_user_values() {
+        let mut settings = Settings::default();
+        policy().apply(&mut settings);
+        assert!(!settings.play_sound);
+        assert!(settings.show_notification);
+        assert_eq!(settings.max_pinned, Some(3));
+        assert!(policy().is_locked("play_sound"));
+        assert!(!policy().is_locked("show_notification"));
+    }
+
+    #[test]
+    fn rejects_changes_to_locked_settings() {
+        assert!(messages("[settings]\nplay_sound = false\nshow_notification = false\n").is_empty());
+        assert_eq!(
+            messages("[settings]\nplay_sound = true\n"),
+            vec!["config.toml:2:14: 错误: 设置 play_sound 已被管理员锁定为 false"]
+        );
+    }
+
+    #[test]
+    fn rejects_forbidden_bindings() {
+        let content = "hotkey = \"^!t\"\n\n[[bindings]]\nhotkey = \"F9\"\naction = \"exit\"\n";
+        assert_eq!(
+            messages(content),
+            vec![
+                "config.toml:1:10: 错误: 快捷键 Ctrl+Alt+T 已被管理员禁止",
+                "config.toml:5:10: 错误: 动作 exit 已被管理员禁止",
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_policies_are_reported() {
+        let err = Policy::parse("[settings]\nvolume = 3\n", Path::new("policy.toml")).unwrap_err();
+        assert!(err.to_string().contains("volume"), "{err}");
+        let err = Policy::parse(
+            "[forbid]\nactions = [\"explode\"]\n",
+            Path::new("policy.toml"),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ConfigError::Parse { line: 2, .. }), "{err}");
+        assert!(Policy::load(Path::new("/nonexistent/policy.toml"))
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,116 @@
+//! 启动失败提示
+//!
+//! 窗口程序没有控制台，启动失败时用消息框告知原因并写入日志文件。
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use window_topmost::{Config, ConfigError, StartupError};
+use windows::core::{w, HSTRING};
+use windows::Win32::UI::WindowsAndMessaging::{
+    MessageBoxW, IDNO, IDOK, IDYES, MB_ICONERROR, MB_ICONWARNING, MB_OK, MB_OKCANCEL,
+    MB_YESNOCANCEL,
+};
+
+/// 用户对配置错误的选择
+pub enum ConfigChoice {
+    /// 打开出错的文件修改后重试
+    Open(PathBuf),
+    /// 把用户配置文件重置为默认配置后重试
+    Reset,
+    /// 退出
+    Exit,
+}
+
+/// 日志文件路径（与配置文件同目录）
+pub fn log_path() -> PathBuf {
+    Config::config_path().with_file_name("window-topmost.log")
+}
+
+/// 追加写入日志文件
+fn write_log(text: &str) {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    if let Ok(mut file) = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path())
+    {
+        let _ = writeln!(file, "[{}] {}\n", secs, text);
+    }
+}
+
+/// 是否是用户自己的配置文件（只有它可以重置）
+fn is_user_config(path: &Path) -> bool {
+    let user = Config::config_path();
+    path == user
+        || matches!(
+            (path.canonicalize(), user.canonicalize()),
+            (Ok(a), Ok(b)) if a == b
+        )
+}
+
+/// 报告启动失败
+///
+/// 配置错误时询问是否打开出错的文件（可能是用户配置、全局配置、被包含的文件或策略文件），
+/// 出错的是用户配置文件时还可以重置；其他错误只显示消息框。
+pub fn report(err: &StartupError) -> ConfigChoice {
+    let text = err.report();
+    write_log(&text);
+
+    let path = match err {
+        StartupError::Config(
+            err @ (ConfigError::Parse { .. }
+            | ConfigError::Read { .. }
+            | ConfigError::Version { .. }
+            | ConfigError::Policy { .. }
+            | ConfigError::IncludeCycle { .. }),
+        ) => err.path(),
+        _ => None,
+    };
+    let Some(path) = path else {
+        let message = format!("{}\n\n日志: {}", text, log_path().display());
+        unsafe {
+            MessageBoxW(
+                None,
+                &HSTRING::from(message),
+                w!("窗口置顶工具 - 启动失败"),
+                MB_OK | MB_ICONERROR,
+            );
+        }
+        return ConfigChoice::Exit;
+    };
+
+    let resettable = is_user_config(path);
+    let (choices, style) = if resettable {
+        (
+            "是：打开配置文件\n否：重置为默认配置\n取消：退出程序",
+            MB_YESNOCANCEL,
+        )
+    } else {
+        ("确定：打开出错的文件\n取消：退出程序", MB_OKCANCEL)
+    };
+    let message = format!(
+        "{}\n\n{}\n\n文件: {}\n日志: {}",
+        text,
+        choices,
+        path.display(),
+        log_path().display()
+    );
+    let answer = unsafe {
+        MessageBoxW(
+            None,
+            &HSTRING::from(message),
+            w!("窗口置顶工具 - 配置错误"),
+            style | MB_ICONWARNING,
+        )
+    };
+    match answer {
+        IDYES | IDOK => ConfigChoice::Open(path.to_path_buf()),
+        IDNO if resettable => ConfigChoice::Reset,
+        _ => ConfigChoice::Exit,
+    }
+}
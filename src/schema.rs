@@ -0,0 +1,176 @@
+//! 配置文件的 JSON Schema
+//!
+//! 供编辑器（如 VS Code 的 Even Better TOML）做补全和检查，由 `config schema` 输出。
+
+use crate::action::Action;
+use crate::config::DEFAULT_CHORD_TIMEOUT;
+use crate::keys::Key;
+use crate::migrate::CURRENT_VERSION;
+use serde_json::{json, Value};
+
+/// 快捷键：字符串、字符串数组或表
+fn hotkey() -> Value {
+    let names: Vec<String> = Key::all().iter().map(Key::name).collect();
+    let modifier = |name: &str| json!({ "type": "boolean", "default": false, "description": name });
+    let sided = |name: &str| {
+        json!({
+            "description": format!("{}，\"left\"、\"right\" 限定左右", name),
+            "default": false,
+            "oneOf": [{ "type": "boolean" }, { "enum": ["left", "right"] }]
+        })
+    };
+    json!({
+        "description": "快捷键，如 \"Ctrl+Alt+T\"，或 [\"F9\", \"Ctrl+F9\"]",
+        "oneOf": [
+            {
+                "type": "string",
+                "examples": ["Ctrl+Space", "Ctrl+Alt+T", "F9", "RAlt+sc:0x35"]
+            },
+            {
+                "type": "array",
+                "description": "首选组合在前，被其他程序占用时依次尝试后面的备选组合",
+                "items": { "type": "string" },
+                "minItems": 1
+            },
+            {
+                "type": "object",
+                "properties": {
+                    "ctrl": sided("Ctrl 键"),
+                    "alt": sided("Alt 键"),
+                    "shift": sided("Shift 键"),
+                    "win": modifier("Win 键"),
+                    "key": {
+                        "type": "string",
+                        "description": "主键（不区分大小写），或 \"vk:0x7C\"、\"sc:0x35\"",
+                        "examples": names
+                    }
+                },
+                "required": ["key"],
+                "additionalProperties": false
+            }
+        ]
+    })
+}
+
+/// `Config` 的 JSON Schema
+pub fn config_schema() -> Value {
+    let actions: Vec<&str> = Action::ALL.iter().map(Action::name).collect();
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "window-topmost 配置文件",
+        "type": "object",
+        "properties": {
+            "version": {
+                "type": "integer",
+                "minimum": 1,
+                "maximum": CURRENT_VERSION,
+                "description": "配置文件版本，由程序维护"
+            },
+            "include": {
+                "type": "array",
+                "items": { "type": "string" },
+                "description": "先读取的其他配置文件，相对路径以本文件所在目录为准"
+            },
+            "hotkey": hotkey(),
+            "settings": {
+                "type": "object",
+                "properties": {
+                    "show_notification": {
+                        "type": "boolean",
+                        "default": true,
+                        "description": "是否显示提示气泡"
+                    },
+                    "play_sound": {
+                        "type": "boolean",
+                        "default": true,
+                        "description": "是否播放提示音"
+                    },
+                    "max_pinned": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "description": "最多同时置顶的窗口数，不写则不限制"
+                    }
+                },
+                "additionalProperties": false
+            },
+            "bindings": {
+                "type": "array",
+                "description": "其他快捷键绑定",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "hotkey": hotkey(),
+                        "action": { "enum": actions }
+                    },
+                    "required": ["hotkey", "action"],
+                    "additionalProperties": false
+                }
+            },
+            "chords": {
+                "type": "array",
+                "description": "组合键：先按引导键，再在限定时间内按第二个键",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "leader": hotkey(),
+                        "timeout": {
+                            "type": "integer",
+                            "minimum": 1,
+                            "default": DEFAULT_CHORD_TIMEOUT,
+                            "description": "等待第二个键的时间（毫秒）"
+                        },
+                        "keys": {
+                            "type": "object",
+                            "description": "第二个键（如 \"T\"、\"Ctrl+T\"）→ 动作",
+                            "additionalProperties": { "enum": actions }
+                        }
+                    },
+                    "required": ["leader", "keys"],
+                    "additionalProperties": false
+                }
+            }
+        },
+        "additionalProperties": false
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::{SETTINGS_FIELDS, TOP_LEVEL};
+
+    fn keys(value: &Value) -> Vec<&str> {
+        let mut keys: Vec<&str> = value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    fn sorted<const N: usize>(mut names: [&str; N]) -> Vec<&str> {
+        names.sort_unstable();
+        names.to_vec()
+    }
+
+    #[test]
+    fn schema_matches_the_config_checker() {
+        let schema = config_schema();
+        let properties = &schema["properties"];
+        assert_eq!(keys(properties), sorted(TOP_LEVEL));
+        assert_eq!(
+            keys(&properties["settings"]["properties"]),
+            sorted(SETTINGS_FIELDS)
+        );
+        assert_eq!(
+            properties["bindings"]["items"]["properties"]["action"]["enum"]
+                .as_array()
+                .unwrap()
+                .len(),
+            Action::ALL.len()
+        );
+        assert_eq!(properties["version"]["maximum"], CURRENT_VERSION);
+    }
+}
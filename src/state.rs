@@ -0,0 +1,127 @@
+//! 置顶窗口登记表
+//!
+//! 只记录由本工具置顶的窗口。窗口原本就是置顶的（由其自身程序设置）不会登记，
+//! 因此取消置顶时不会误伤这些窗口。
+
+use crate::action::Source;
+use crate::platform::{Process, WindowBackend, WindowId};
+use std::time::SystemTime;
+
+/// 由本工具置顶的窗口
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinnedWindow {
+    /// 窗口句柄
+    pub window: WindowId,
+    /// 所属进程
+    pub process: Option<Process>,
+    /// 置顶时的标题
+    pub title: String,
+    /// 置顶时间
+    pub pinned_at: SystemTime,
+    /// 触发来源
+    pub source: Source,
+}
+
+/// 程序状态
+#[derive(Debug, Default)]
+pub struct AppState {
+    pinned: Vec<PinnedWindow>,
+}
+
+impl AppState {
+    /// 空状态
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记置顶的窗口（已登记的窗口会被替换）
+    pub fn record_pin(&mut self, entry: PinnedWindow) {
+        self.remove(entry.window);
+        self.pinned.push(entry);
+    }
+
+    /// 移除登记，返回原记录
+    pub fn remove(&mut self, window: WindowId) -> Option<PinnedWindow> {
+        let index = self.pinned.iter().position(|p| p.window == window)?;
+        Some(self.pinned.remove(index))
+    }
+
+    /// 窗口是否由本工具置顶
+    pub fn is_ours(&self, window: WindowId) -> bool {
+        self.get(window).is_some()
+    }
+
+    /// 查找登记记录
+    pub fn get(&self, window: WindowId) -> Option<&PinnedWindow> {
+        self.pinned.iter().find(|p| p.window == window)
+    }
+
+    /// 所有登记的窗口，按置顶先后排列
+    pub fn pinned(&self) -> &[PinnedWindow] {
+        &self.pinned
+    }
+
+    /// 清理已销毁或已被外部取消置顶的窗口，返回被移除的记录
+    pub fn prune(&mut self, backend: &dyn WindowBackend) -> Vec<PinnedWindow> {
+        let (alive, gone) = std::mem::take(&mut self.pinned)
+            .into_iter()
+            .partition(|p| backend.exists(p.window) && backend.is_topmost(p.window));
+        self.pinned = alive;
+        gone
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::sim::SimDesktop;
+
+    fn entry(window: WindowId, title: &str) -> PinnedWindow {
+        PinnedWindow {
+            window,
+            process: None,
+            title: title.into(),
+            pinned_at: SystemTime::now(),
+            source: Source::Hotkey,
+        }
+    }
+
+    #[test]
+    fn prune_drops_destroyed_and_externally_unpinned_windows() {
+        let mut desktop = SimDesktop::new();
+        let a = desktop.open("a");
+        let b = desktop.open("b");
+        let c = desktop.open("c");
+        for w in [a, b, c] {
+            desktop.set_topmost(w, true).unwrap();
+        }
+
+        let mut state = AppState::new();
+        state.record_pin(entry(a, "a"));
+        state.record_pin(entry(b, "b"));
+        state.record_pin(entry(c, "c"));
+
+        desktop.close(a);
+        desktop.set_topmost(b, false).unwrap();
+
+        let gone: Vec<_> = state
+            .prune(&desktop)
+            .into_iter()
+            .map(|p| p.window)
+            .collect();
+        assert_eq!(gone, vec![a, b]);
+        assert!(state.is_ours(c));
+        assert_eq!(state.pinned().len(), 1);
+    }
+
+    #[test]
+    fn record_pin_replaces_existing_entry() {
+        let mut state = AppState::new();
+        state.record_pin(entry(WindowId(1), "旧标题"));
+        state.record_pin(entry(WindowId(1), "新标题"));
+        assert_eq!(state.pinned().len(), 1);
+        assert_eq!(state.get(WindowId(1)).unwrap().title, "新标题");
+        assert!(state.remove(WindowId(1)).is_some());
+        assert!(!state.is_ours(WindowId(1)));
+    }
+}
@@ -1,11 +1,11 @@
 //! 系统托盘模块
+//!
+//! 托盘窗口通过窗口用户数据（GWLP_USERDATA）持有 [`App`]，
+//! 其他线程通过 [`Notifier`] 向托盘窗口投递消息。
 
-use crate::config::Config;
-use anyhow::Result;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use std::cell::RefCell;
-use windows::core::w;
+use crate::app::App;
+use window_topmost::{location, Action, Config, Policy, Source, TrayError};
+use windows::core::{w, PCWSTR};
 use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
 use windows::Win32::System::LibraryLoader::GetModuleHandleW;
 use windows::Win32::UI::Shell::{
@@ -14,49 +14,125 @@ use windows::Win32::UI::Shell::{
 };
 use windows::Win32::UI::WindowsAndMessaging::{
     AppendMenuW, CreatePopupMenu, CreateWindowExW, DefWindowProcW, DestroyMenu, DestroyWindow,
-    GetCursorPos, LoadIconW, PostQuitMessage, RegisterClassW, SetForegroundWindow,
-    TrackPopupMenu, CS_HREDRAW, CS_VREDRAW, CW_USEDEFAULT, IDI_APPLICATION, MF_STRING,
-    TPM_BOTTOMALIGN, TPM_LEFTALIGN, TPM_RIGHTBUTTON, WINDOW_EX_STYLE, WM_COMMAND, WM_DESTROY,
-    WM_LBUTTONDBLCLK, WM_RBUTTONUP, WM_USER, WNDCLASSW, WS_OVERLAPPEDWINDOW,
+    GetCursorPos, GetWindowLongPtrW, LoadIconW, PostMessageW, PostQuitMessage, RegisterClassW,
+    SetForegroundWindow, SetWindowLongPtrW, TrackPopupMenu, CS_HREDRAW, CS_VREDRAW,
+    CW_USEDEFAULT, GWLP_USERDATA, IDI_APPLICATION, MF_GRAYED, MF_SEPARATOR, MF_STRING, TPM_BOTTOMALIGN, TPM_LEFTALIGN,
+    TPM_RIGHTBUTTON, WINDOW_EX_STYLE, WM_COMMAND, WM_DESTROY, WM_HOTKEY, WM_LBUTTONDBLCLK, WM_RBUTTONUP,
+    WM_TIMER, WM_USER, WNDCLASSW, WS_OVERLAPPEDWINDOW,
 };
 
 /// 托盘消息
 const WM_TRAYICON: u32 = WM_USER + 1;
 
-/// 菜单项 ID
-const MENU_TOGGLE: u16 = 1;
-const MENU_CONFIG: u16 = 2;
-const MENU_RELOAD: u16 = 3;
-const MENU_EXIT: u16 = 4;
+/// 通知消息（LPARAM 为 `Box<(String, String)>`）
+const WM_NOTIFY_USER: u32 = WM_USER + 2;
 
-// 全局变量（用于窗口过程）
-thread_local! {
-    static RUNNING_FLAG: RefCell<Option<Arc<AtomicBool>>> = const { RefCell::new(None) };
-    static GLOBAL_CONFIG: RefCell<Option<Config>> = const { RefCell::new(None) };
-    static TRAY_HWND: RefCell<Option<HWND>> = const { RefCell::new(None) };
-}
+/// 动作消息（WPARAM 为动作下标，LPARAM 为来源下标）
+const WM_DISPATCH: u32 = WM_USER + 3;
+
+/// 动作来源（消息中使用下标）
+const SOURCES: [Source; 3] = [Source::Hotkey, Source::Menu, Source::External];
+
+/// 菜单项（菜单 ID 为下标 + 1）
+const MENU_ITEMS: [(Action, PCWSTR); 5] = [
+    (Action::Toggle, w!("📌 置顶当前窗口")),
+    (Action::UnpinAll, w!("📍 取消全部置顶")),
+    (Action::OpenConfig, w!("⚙️ 打开配置文件")),
+    (Action::Reload, w!("🔄 重新加载配置")),
+    (Action::Exit, w!("❌ 退出")),
+];
 
 /// 托盘句柄
 pub struct TrayHandle {
     hwnd: HWND,
 }
 
+impl TrayHandle {
+    /// 托盘窗口
+    pub fn hwnd(&self) -> HWND {
+        self.hwnd
+    }
+
+    /// 把程序状态交给托盘窗口
+    pub fn attach(&mut self, app: App) {
+        let app = Box::into_raw(Box::new(app));
+        unsafe {
+            let old = SetWindowLongPtrW(self.hwnd, GWLP_USERDATA, app as isize);
+            drop_app(old);
+        }
+    }
+
+    /// 可跨线程使用的消息投递器
+    pub fn notifier(&self) -> Notifier {
+        Notifier {
+            hwnd: self.hwnd.0 as isize,
+        }
+    }
+}
+
 impl Drop for TrayHandle {
     fn drop(&mut self) {
         remove_tray_icon(self.hwnd);
         unsafe {
+            // 先释放程序状态（注销快捷键），再销毁窗口
+            let app = SetWindowLongPtrW(self.hwnd, GWLP_USERDATA, 0);
+            drop_app(app);
             let _ = DestroyWindow(self.hwnd);
         }
     }
 }
 
-/// 创建系统托盘
-pub fn create_tray(running: Arc<AtomicBool>, config: Config) -> Result<TrayHandle> {
-    RUNNING_FLAG.with(|f| *f.borrow_mut() = Some(running));
-    GLOBAL_CONFIG.with(|c| *c.borrow_mut() = Some(config.clone()));
+/// 释放窗口用户数据中的程序状态
+unsafe fn drop_app(ptr: isize) {
+    if ptr != 0 {
+        drop(Box::from_raw(ptr as *mut App));
+    }
+}
+
+/// 托盘窗口持有的程序状态
+unsafe fn app_mut<'a>(hwnd: HWND) -> Option<&'a mut App> {
+    (GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut App).as_mut()
+}
 
+/// 向托盘窗口投递通知和动作
+///
+/// 只保存窗口句柄，可以在任意线程使用。
+#[derive(Debug, Clone, Copy)]
+pub struct Notifier {
+    hwnd: isize,
+}
+
+impl Notifier {
+    fn hwnd(&self) -> HWND {
+        HWND(self.hwnd as *mut _)
+    }
+
+    /// 显示通知气泡
+    pub fn notify(&self, title: &str, message: &str) {
+        let payload = Box::into_raw(Box::new((title.to_string(), message.to_string())));
+        unsafe {
+            let posted = PostMessageW(self.hwnd(), WM_NOTIFY_USER, WPARAM(0), LPARAM(payload as isize));
+            if posted.is_err() {
+                // 窗口已销毁，消息不会被处理
+                drop(Box::from_raw(payload));
+            }
+        }
+    }
+
+    /// 在托盘线程上执行动作
+    pub fn dispatch(&self, action: Action, source: Source) {
+        let action = Action::ALL.iter().position(|&a| a == action).unwrap_or_default();
+        let source = SOURCES.iter().position(|&s| s == source).unwrap_or_default();
+        unsafe {
+            let _ = PostMessageW(self.hwnd(), WM_DISPATCH, WPARAM(action), LPARAM(source as isize));
+        }
+    }
+}
+
+/// 创建系统托盘
+pub fn create_tray(config: &Config) -> Result<TrayHandle, TrayError> {
     unsafe {
-        let instance = GetModuleHandleW(None)?;
+        let instance = GetModuleHandleW(None).map_err(|e| TrayError::CreateWindow(e.into()))?;
 
         let class_name = w!("WindowTopMostTray");
         let wc = WNDCLASSW {
@@ -81,24 +157,20 @@ pub fn create_tray(running: Arc<AtomicBool>, config: Config) -> Result<TrayHandl
             None,
             instance,
             None,
-        )?;
+        )
+        .map_err(|e| TrayError::CreateWindow(e.into()))?;
 
-        TRAY_HWND.with(|h| *h.borrow_mut() = Some(hwnd));
-        add_tray_icon(hwnd, &config)?;
+        add_tray_icon(hwnd, config)?;
 
         Ok(TrayHandle { hwnd })
     }
 }
 
 /// 添加托盘图标
-fn add_tray_icon(hwnd: HWND, config: &Config) -> Result<()> {
+fn add_tray_icon(hwnd: HWND, config: &Config) -> Result<(), TrayError> {
     unsafe {
         // 使用 None 加载系统默认图标
-        let icon = LoadIconW(None, IDI_APPLICATION)?;
-
-        let tip = format!("窗口置顶工具
-快捷键: {}", config.hotkey.display());
-        let tip_wide: Vec<u16> = tip.encode_utf16().chain(std::iter::once(0)).collect();
+        let icon = LoadIconW(None, IDI_APPLICATION).map_err(|e| TrayError::LoadIcon(e.into()))?;
 
         let mut nid = NOTIFYICONDATAW {
             cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
@@ -109,17 +181,44 @@ fn add_tray_icon(hwnd: HWND, config: &Config) -> Result<()> {
             hIcon: icon,
             ..Default::default()
         };
-
-        let tip_len = tip_wide.len().min(nid.szTip.len());
-        nid.szTip[..tip_len].copy_from_slice(&tip_wide[..tip_len]);
+        set_tip(&mut nid, config);
 
         if !Shell_NotifyIconW(NIM_ADD, &nid).as_bool() {
-            anyhow::bail!("Failed to add tray icon");
+            return Err(TrayError::AddIcon);
         }
     }
     Ok(())
 }
 
+/// 更新托盘提示文字（配置变化后调用）
+pub fn update_tooltip(hwnd: HWND, config: &Config) {
+    let mut nid = NOTIFYICONDATAW {
+        cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+        hWnd: hwnd,
+        uID: 1,
+        uFlags: NIF_TIP,
+        ..Default::default()
+    };
+    set_tip(&mut nid, config);
+    unsafe {
+        let _ = Shell_NotifyIconW(NIM_MODIFY, &nid);
+    }
+}
+
+/// 托盘提示文字：快捷键和配置文件位置
+fn set_tip(nid: &mut NOTIFYICONDATAW, config: &Config) {
+    let head = format!("窗口置顶工具\n快捷键: {}\n配置: ", config.hotkey.display());
+    // szTip 最多 128 个 UTF-16 字符（含结尾的 0），路径太长时只显示末尾
+    let room = (nid.szTip.len() - 1).saturating_sub(head.encode_utf16().count());
+    let path = location::abbreviate(&Config::config_path(), room);
+    let tip = format!("{}{}", head, path);
+    let tip_wide: Vec<u16> = tip.encode_utf16().chain(std::iter::once(0)).collect();
+
+    let tip_len = tip_wide.len().min(nid.szTip.len());
+    nid.szTip[..tip_len].copy_from_slice(&tip_wide[..tip_len]);
+    nid.szTip[nid.szTip.len() - 1] = 0;
+}
+
 /// 移除托盘图标
 fn remove_tray_icon(hwnd: HWND) {
     unsafe {
@@ -133,47 +232,50 @@ fn remove_tray_icon(hwnd: HWND) {
     }
 }
 
-/// 显示通知气泡
-pub fn show_notification(title: &str, message: &str) {
-    TRAY_HWND.with(|h| {
-        if let Some(hwnd) = *h.borrow() {
-            unsafe {
-                let title_wide: Vec<u16> = title.encode_utf16().chain(std::iter::once(0)).collect();
-                let msg_wide: Vec<u16> = message.encode_utf16().chain(std::iter::once(0)).collect();
+/// 显示通知气泡（须在托盘线程调用，其他线程使用 [`Notifier`]）
+pub fn show_notification(hwnd: HWND, title: &str, message: &str) {
+    unsafe {
+        let title_wide: Vec<u16> = title.encode_utf16().chain(std::iter::once(0)).collect();
+        let msg_wide: Vec<u16> = message.encode_utf16().chain(std::iter::once(0)).collect();
 
-                let mut nid = NOTIFYICONDATAW {
-                    cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
-                    hWnd: hwnd,
-                    uID: 1,
-                    uFlags: NIF_INFO,
-                    dwInfoFlags: NIIF_INFO,
-                    ..Default::default()
-                };
+        let mut nid = NOTIFYICONDATAW {
+            cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+            hWnd: hwnd,
+            uID: 1,
+            uFlags: NIF_INFO,
+            dwInfoFlags: NIIF_INFO,
+            ..Default::default()
+        };
 
-                let title_len = title_wide.len().min(nid.szInfoTitle.len());
-                nid.szInfoTitle[..title_len].copy_from_slice(&title_wide[..title_len]);
+        let title_len = title_wide.len().min(nid.szInfoTitle.len());
+        nid.szInfoTitle[..title_len].copy_from_slice(&title_wide[..title_len]);
 
-                let msg_len = msg_wide.len().min(nid.szInfo.len());
-                nid.szInfo[..msg_len].copy_from_slice(&msg_wide[..msg_len]);
+        let msg_len = msg_wide.len().min(nid.szInfo.len());
+        nid.szInfo[..msg_len].copy_from_slice(&msg_wide[..msg_len]);
 
-                let _ = Shell_NotifyIconW(NIM_MODIFY, &nid);
-            }
-        }
-    });
+        let _ = Shell_NotifyIconW(NIM_MODIFY, &nid);
+    }
 
     #[cfg(debug_assertions)]
     println!("[{}] {}", title, message);
 }
 
-/// 显示右键菜单
-fn show_context_menu(hwnd: HWND) {
+/// 显示右键菜单，管理员禁止的动作显示为灰色
+fn show_context_menu(hwnd: HWND, policy: Option<&Policy>) {
     unsafe {
         let menu = CreatePopupMenu().unwrap();
 
-        AppendMenuW(menu, MF_STRING, MENU_TOGGLE as usize, w!("📌 置顶当前窗口")).ok();
-        AppendMenuW(menu, MF_STRING, MENU_CONFIG as usize, w!("⚙️ 打开配置文件")).ok();
-        AppendMenuW(menu, MF_STRING, MENU_RELOAD as usize, w!("🔄 重新加载配置")).ok();
-        AppendMenuW(menu, MF_STRING, MENU_EXIT as usize, w!("❌ 退出")).ok();
+        for (index, (action, label)) in MENU_ITEMS.iter().enumerate() {
+            let mut flags = MF_STRING;
+            if policy.is_some_and(|p| p.forbids_action(*action)) {
+                flags |= MF_GRAYED;
+            }
+            AppendMenuW(menu, flags, index + 1, *label).ok();
+        }
+        if policy.is_some_and(|p| p.locked().next().is_some()) {
+            AppendMenuW(menu, MF_SEPARATOR, 0, None).ok();
+            AppendMenuW(menu, MF_STRING | MF_GRAYED, 0, w!("🔒 部分设置已被管理员锁定")).ok();
+        }
 
         let mut pt = windows::Win32::Foundation::POINT::default();
         let _ = GetCursorPos(&mut pt);
@@ -184,33 +286,12 @@ fn show_context_menu(hwnd: HWND) {
 }
 
 /// 处理菜单命令
-fn handle_menu_command(cmd: u16) {
-    match cmd {
-        MENU_TOGGLE => {
-            GLOBAL_CONFIG.with(|c| {
-                if let Some(config) = c.borrow().as_ref() {
-                    crate::window::toggle_topmost(config);
-                }
-            });
-        }
-        MENU_CONFIG => {
-            let config_path = crate::config::Config::config_path();
-            let _ = std::process::Command::new("notepad.exe")
-                .arg(&config_path)
-                .spawn();
-        }
-        MENU_RELOAD => {
-            show_notification("提示", "请重启程序以应用新配置");
-        }
-        MENU_EXIT => {
-            RUNNING_FLAG.with(|f| {
-                if let Some(running) = f.borrow().as_ref() {
-                    running.store(false, Ordering::SeqCst);
-                }
-            });
-            unsafe { PostQuitMessage(0); }
-        }
-        _ => {}
+fn handle_menu_command(app: &mut App, cmd: u16) {
+    let action = (cmd as usize)
+        .checked_sub(1)
+        .and_then(|index| MENU_ITEMS.get(index));
+    if let Some((action, _)) = action {
+        app.dispatch(*action, Source::Menu);
     }
 }
 
@@ -225,14 +306,45 @@ unsafe extern "system" fn window_proc(
         WM_TRAYICON => {
             let event = lparam.0 as u32;
             match event {
-                WM_RBUTTONUP => show_context_menu(hwnd),
-                WM_LBUTTONDBLCLK => handle_menu_command(MENU_TOGGLE),
+                WM_RBUTTONUP => show_context_menu(hwnd, app_mut(hwnd).map(|app| app.policy())),
+                WM_LBUTTONDBLCLK => {
+                    if let Some(app) = app_mut(hwnd) {
+                        app.dispatch(Action::Toggle, Source::Menu);
+                    }
+                }
                 _ => {}
             }
             LRESULT(0)
         }
         WM_COMMAND => {
-            handle_menu_command((wparam.0 & 0xFFFF) as u16);
+            if let Some(app) = app_mut(hwnd) {
+                handle_menu_command(app, (wparam.0 & 0xFFFF) as u16);
+            }
+            LRESULT(0)
+        }
+        WM_HOTKEY => {
+            if let Some(app) = app_mut(hwnd) {
+                app.on_hotkey(wparam.0 as i32);
+            }
+            LRESULT(0)
+        }
+        WM_TIMER => {
+            if let Some(app) = app_mut(hwnd) {
+                app.on_timer(wparam.0);
+            }
+            LRESULT(0)
+        }
+        WM_NOTIFY_USER => {
+            let payload = Box::from_raw(lparam.0 as *mut (String, String));
+            show_notification(hwnd, &payload.0, &payload.1);
+            LRESULT(0)
+        }
+        WM_DISPATCH => {
+            let action = Action::ALL.get(wparam.0);
+            let source = SOURCES.get(lparam.0 as usize);
+            if let (Some(app), Some(&action), Some(&source)) = (app_mut(hwnd), action, source) {
+                app.dispatch(action, source);
+            }
             LRESULT(0)
         }
         WM_DESTROY => {
@@ -0,0 +1,71 @@
+//! 配置文件变化检测
+//!
+//! 通过比较修改时间和文件大小判断文件是否变化，由调用者定期调用 [`FileWatcher::changed`]。
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// 文件状态：修改时间和大小，文件不存在时为 None
+type Stamp = Option<(SystemTime, u64)>;
+
+/// 文件监视器
+#[derive(Debug)]
+pub struct FileWatcher {
+    path: PathBuf,
+    last: Stamp,
+}
+
+impl FileWatcher {
+    /// 以文件当前状态为基准
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let last = stamp(&path);
+        Self { path, last }
+    }
+
+    /// 监视的文件
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 与上次调用相比文件是否有变化（删除也算）
+    pub fn changed(&mut self) -> bool {
+        let now = stamp(&self.path);
+        if now == self.last {
+            return false;
+        }
+        self.last = now;
+        true
+    }
+}
+
+fn stamp(path: &Path) -> Stamp {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_writes_and_removal() {
+        let dir = std::env::temp_dir().join(format!("window-topmost-watch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        fs::write(&path, "a = 1\n").unwrap();
+
+        let mut watcher = FileWatcher::new(&path);
+        assert!(!watcher.changed());
+
+        // 大小变化即可检测到，不依赖时间戳精度
+        fs::write(&path, "a = 12\n").unwrap();
+        assert!(watcher.changed());
+        assert!(!watcher.changed());
+
+        fs::remove_file(&path).unwrap();
+        assert!(watcher.changed());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,101 +1,210 @@
 //! 窗口操作模块
 
-use crate::config::Config;
-use crate::tray;
-use windows::Win32::Foundation::HWND;
-use windows::Win32::UI::WindowsAndMessaging::{
-    GetForegroundWindow, GetWindowLongW, SetWindowPos, GWL_EXSTYLE, HWND_NOTOPMOST,
-    HWND_TOPMOST, SWP_NOMOVE, SWP_NOSIZE, WS_EX_TOPMOST,
-};
-
-/// 切换当前窗口的置顶状态
-pub fn toggle_topmost(config: &Config) {
-    unsafe {
-        // 获取当前前台窗口
-        let hwnd = GetForegroundWindow();
-
-        if hwnd.0 == std::ptr::null_mut() {
-            tray::show_notification("窗口置顶工具", "未找到活动窗口！");
-            return;
+use crate::config::Settings;
+use crate::platform::{WindowBackend, WindowId};
+
+/// 无标题窗口的显示名称
+pub(crate) const UNTITLED: &str = "(无标题)";
+
+/// 置顶切换结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToggleOutcome {
+    /// 没有前台窗口
+    NoWindow,
+    /// 已置顶
+    Pinned { title: String },
+    /// 已取消置顶
+    Unpinned { title: String },
+    /// 窗口原本就是置顶的（不是由本工具置顶），未做修改
+    NotOurs { title: String },
+    /// 已达到置顶窗口数上限，未做修改
+    LimitReached { max: usize },
+    /// 系统调用失败
+    Failed,
+}
+
+/// 操作反馈（提示音 + 通知）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Feedback {
+    /// 提示音 (频率, 时长)
+    pub beep: Option<(u32, u32)>,
+    /// 通知 (标题, 内容)
+    pub notification: Option<(String, String)>,
+}
+
+impl ToggleOutcome {
+    /// 成功修改置顶状态后的结果
+    pub fn completed(pinned: bool, title: Option<String>) -> Self {
+        let title = title.unwrap_or_else(|| UNTITLED.to_string());
+        if pinned {
+            ToggleOutcome::Pinned { title }
+        } else {
+            ToggleOutcome::Unpinned { title }
         }
+    }
 
-        // 检查是否已置顶
-        let ex_style = GetWindowLongW(hwnd, GWL_EXSTYLE) as u32;
-        let is_topmost = (ex_style & WS_EX_TOPMOST.0) != 0;
-
-        // 获取窗口标题用于提示
-        let title = get_window_title(hwnd);
-
-        if is_topmost {
-            // 取消置顶
-            let result = SetWindowPos(
-                hwnd,
-                HWND_NOTOPMOST,
-                0,
-                0,
-                0,
-                0,
-                SWP_NOMOVE | SWP_NOSIZE,
-            );
-
-            if result.is_ok() {
-                if config.settings.play_sound {
-                    play_beep(600, 100);
-                }
-                if config.settings.show_notification {
-                    tray::show_notification("取消置顶", &title);
-                }
+    /// 生成用户反馈
+    pub fn feedback(&self, settings: &Settings) -> Feedback {
+        let (frequency, label, title) = match self {
+            ToggleOutcome::NoWindow => {
+                // 找不到窗口时总是提示
+                return Feedback {
+                    beep: None,
+                    notification: Some(("窗口置顶工具".into(), "未找到活动窗口！".into())),
+                };
             }
-        } else {
-            // 设置置顶
-            let result = SetWindowPos(
-                hwnd,
-                HWND_TOPMOST,
-                0,
-                0,
-                0,
-                0,
-                SWP_NOMOVE | SWP_NOSIZE,
-            );
-
-            if result.is_ok() {
-                if config.settings.play_sound {
-                    play_beep(800, 100);
-                }
-                if config.settings.show_notification {
-                    tray::show_notification("已置顶", &title);
-                }
+            ToggleOutcome::NotOurs { title } => {
+                // 没有做任何修改，同样总是提示
+                return Feedback {
+                    beep: None,
+                    notification: Some((
+                        "未取消置顶".into(),
+                        format!("{} 不是由本工具置顶的", title),
+                    )),
+                };
             }
+            ToggleOutcome::LimitReached { max } => {
+                return Feedback {
+                    beep: None,
+                    notification: Some((
+                        "未置顶".into(),
+                        format!("最多只能同时置顶 {} 个窗口", max),
+                    )),
+                };
+            }
+            ToggleOutcome::Failed => return Feedback::default(),
+            ToggleOutcome::Pinned { title } => (800, "已置顶", title),
+            ToggleOutcome::Unpinned { title } => (600, "取消置顶", title),
+        };
+
+        Feedback {
+            beep: settings.play_sound.then_some((frequency, 100)),
+            notification: settings
+                .show_notification
+                .then(|| (label.to_string(), title.clone())),
         }
     }
 }
 
-/// 获取窗口标题
-fn get_window_title(hwnd: HWND) -> String {
-    use windows::Win32::UI::WindowsAndMessaging::{GetWindowTextLengthW, GetWindowTextW};
+/// 切换前台窗口的置顶状态
+pub fn toggle(backend: &mut dyn WindowBackend) -> ToggleOutcome {
+    match backend.foreground() {
+        Some(window) => set_topmost(backend, window, None),
+        None => ToggleOutcome::NoWindow,
+    }
+}
 
-    unsafe {
-        let len = GetWindowTextLengthW(hwnd);
-        if len == 0 {
-            return "(无标题)".to_string();
-        }
+/// 修改窗口的置顶状态，`topmost` 为 None 时切换
+pub fn set_topmost(
+    backend: &mut dyn WindowBackend,
+    window: WindowId,
+    topmost: Option<bool>,
+) -> ToggleOutcome {
+    let was_topmost = backend.is_topmost(window);
+    let target = topmost.unwrap_or(!was_topmost);
+    let title = backend.title(window);
+    match backend.set_topmost(window, target) {
+        Ok(()) => ToggleOutcome::completed(target, title),
+        Err(_) => ToggleOutcome::Failed,
+    }
+}
 
-        let mut buffer = vec![0u16; (len + 1) as usize];
-        let copied = GetWindowTextW(hwnd, &mut buffer);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::sim::SimDesktop;
 
-        if copied > 0 {
-            String::from_utf16_lossy(&buffer[..copied as usize])
-        } else {
-            "(无标题)".to_string()
-        }
+    #[test]
+    fn completed_outcome_uses_placeholder_title() {
+        assert_eq!(
+            ToggleOutcome::completed(true, Some("记事本".into())),
+            ToggleOutcome::Pinned {
+                title: "记事本".into()
+            }
+        );
+        assert_eq!(
+            ToggleOutcome::completed(false, None),
+            ToggleOutcome::Unpinned {
+                title: UNTITLED.into()
+            }
+        );
+    }
+
+    #[test]
+    fn feedback_respects_settings() {
+        let quiet = Settings {
+            show_notification: false,
+            play_sound: false,
+            ..Settings::default()
+        };
+        let pinned = ToggleOutcome::Pinned { title: "a".into() };
+        assert_eq!(pinned.feedback(&quiet), Feedback::default());
+
+        let loud = Settings::default();
+        let unpinned = ToggleOutcome::Unpinned { title: "b".into() };
+        assert_eq!(
+            unpinned.feedback(&loud),
+            Feedback {
+                beep: Some((600, 100)),
+                notification: Some(("取消置顶".into(), "b".into())),
+            }
+        );
+    }
+
+    #[test]
+    fn missing_window_is_always_reported() {
+        let quiet = Settings {
+            show_notification: false,
+            play_sound: false,
+            ..Settings::default()
+        };
+        assert!(ToggleOutcome::NoWindow
+            .feedback(&quiet)
+            .notification
+            .is_some());
+        assert_eq!(ToggleOutcome::Failed.feedback(&quiet), Feedback::default());
+
+        let foreign = ToggleOutcome::NotOurs { title: "c".into() };
+        assert!(foreign.feedback(&quiet).notification.is_some());
+    }
+
+    #[test]
+    fn pin_focus_another_window_then_unpin() {
+        let mut desktop = SimDesktop::new();
+        let notes = desktop.open("笔记");
+        let browser = desktop.open("浏览器");
+
+        desktop.focus(notes);
+        assert_eq!(
+            toggle(&mut desktop),
+            ToggleOutcome::Pinned {
+                title: "笔记".into()
+            }
+        );
+
+        // 切换到其他窗口后，置顶窗口仍在最上方
+        desktop.focus(browser);
+        assert_eq!(desktop.z_order(), vec![notes, browser]);
+
+        desktop.focus(notes);
+        assert_eq!(
+            toggle(&mut desktop),
+            ToggleOutcome::Unpinned {
+                title: "笔记".into()
+            }
+        );
+        desktop.focus(browser);
+        assert_eq!(desktop.z_order(), vec![browser, notes]);
     }
-}
 
-/// 播放提示音
-fn play_beep(frequency: u32, duration: u32) {
-    use windows::Win32::System::Diagnostics::Debug::Beep;
+    #[test]
+    fn toggle_reports_missing_window_and_failures() {
+        let mut desktop = SimDesktop::new();
+        assert_eq!(toggle(&mut desktop), ToggleOutcome::NoWindow);
 
-    unsafe {
-        let _ = Beep(frequency, duration);
+        let admin = desktop.open("");
+        desktop.protect(admin);
+        assert_eq!(toggle(&mut desktop), ToggleOutcome::Failed);
+        assert!(!desktop.is_topmost(admin));
     }
 }